@@ -0,0 +1,40 @@
+use clap::ArgMatches;
+use colored::Colorize;
+use glit_core::cache::Cache;
+use std::{process, time::Duration};
+
+use crate::utils::cache_dir;
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub enum CacheCommand {
+    List,
+    /// Delete the mirrors not fetched for this long
+    Prune(Duration),
+    Clear,
+}
+
+pub struct CacheCommandHandler {}
+
+impl CacheCommandHandler {
+    pub fn config(subcommand_match: &ArgMatches) -> (Cache, CacheCommand) {
+        let root = cache_dir(subcommand_match).unwrap_or_else(|| {
+            eprintln!(
+                "{} No cache folder, set --cache-dir or GLIT_CACHE_DIR",
+                "Error :".red().bold()
+            );
+            process::exit(2)
+        });
+
+        let command = match subcommand_match.subcommand() {
+            Some(("prune", prune_match)) => {
+                let days = prune_match.get_one::<u64>("older_than").unwrap().to_owned();
+                CacheCommand::Prune(Duration::from_secs(days * DAY))
+            }
+            Some(("clear", _)) => CacheCommand::Clear,
+            _ => CacheCommand::List,
+        };
+
+        (Cache::new(root), command)
+    }
+}
@@ -1,7 +1,38 @@
 use colored::Colorize;
-use glit_core::{config::GlobalConfig, org::Org, repo::Repository, user::User};
-use serde_json;
-use std::{fs, marker::PhantomData, path::PathBuf, str::FromStr};
+use glit_core::{
+    config::{ExportFormat, GlobalConfig},
+    error::{Error, Failure},
+    org::Org,
+    repo::Repository,
+    scan::ScanItem,
+    types::RepoName,
+    user::User,
+};
+use serde::Serialize;
+use serde_json::{self, json, Value};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crate::utils::{exit_on_error, format_datetime};
+
+/// Line of the CSV and TSV exports.
+type Row = [String; 7];
+
+/// Columns of the CSV and TSV exports.
+const HEADER: [&str; 7] = [
+    "repository",
+    "branch",
+    "author",
+    "mail",
+    "commits",
+    "first_seen",
+    "last_seen",
+];
 
 pub struct Exporter<T> {
     global_config: GlobalConfig,
@@ -12,64 +43,449 @@ impl<T> Exporter<T> {
     pub fn new(global_config: GlobalConfig) -> Self {
         Self {
             global_config,
-            _phantom_data: PhantomData::default(),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize> Exporter<T> {
+    /// Write `data` to the output in the chosen format, `name` being the file
+    /// name used when the output is a folder. `repositories` hands each
+    /// repository of `data` to its argument.
+    fn export(self, data: &T, name: &str, repositories: impl Fn(&mut dyn FnMut(&Repository))) {
+        let format = self.global_config.format;
+
+        if let Some(path) = output_path(&self.global_config, name) {
+            let content = match format {
+                ExportFormat::Json => serde_json::to_string_pretty(data).unwrap(),
+                ExportFormat::Ndjson => {
+                    let mut lines = String::new();
+                    repositories(&mut |repo| {
+                        for record in records(repo) {
+                            lines.push_str(&record.to_string());
+                            lines.push('\n');
+                        }
+                    });
+                    lines
+                }
+                ExportFormat::Csv | ExportFormat::Tsv => {
+                    let mut rows_of_data = Vec::new();
+                    repositories(&mut |repo| rows_of_data.extend(rows(repo)));
+                    match format {
+                        ExportFormat::Csv => table(rows_of_data, ',', csv_field),
+                        _ => table(rows_of_data, '\t', tsv_field),
+                    }
+                }
+            };
+            exit_on_error(fs::write(path.as_path(), content).map_err(Error::Io));
+
+            println!("File -> {}", path.to_str().unwrap().yellow());
         }
     }
 }
 
 impl Exporter<Repository> {
     pub fn export_repo(self, data: &Repository) {
-        let output = self.global_config.output;
+        self.export(data, "repo", |each| each(data));
+    }
+}
 
-        if !output.is_empty() {
-            let mut path = PathBuf::from_str(&output).unwrap();
+impl Exporter<User> {
+    pub fn export_user(self, data: &User) {
+        // The NDJSON lines are written by `NdjsonWriter` during the scan
+        if self.global_config.format == ExportFormat::Ndjson {
+            return;
+        }
 
-            if path.is_dir() {
-                path.set_file_name("repo.json");
-            }
+        self.export(data, "user", |each| {
+            data.repositories_data
+                .iter()
+                .for_each(|repo| each(repo.value()))
+        });
+    }
+}
 
-            let json_value = serde_json::to_string_pretty(&data.branch_data).unwrap();
-            fs::write(path.as_path(), json_value).unwrap();
+impl Exporter<Org> {
+    pub fn export_org(self, data: &Org) {
+        // The NDJSON lines are written by `NdjsonWriter` during the scan
+        if self.global_config.format == ExportFormat::Ndjson {
+            return;
+        }
 
-            println!("File -> {}", path.to_str().unwrap().yellow());
+        self.export(data, "org", |each| {
+            data.repositories_data
+                .iter()
+                .for_each(|repo| each(repo.value()))
+        });
+    }
+}
+
+/// NDJSON output of a user or org scan, the lines of each repository being
+/// written as soon as it is walked.
+pub struct NdjsonWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl NdjsonWriter {
+    /// Writer of the output file, `None` unless the output is set with the NDJSON format.
+    pub fn create(global_config: &GlobalConfig, name: &str) -> Option<Self> {
+        if global_config.format != ExportFormat::Ndjson {
+            return None;
+        }
+
+        output_path(global_config, name).map(|path| Self {
+            file: BufWriter::new(exit_on_error(File::create(&path).map_err(Error::Io))),
+            path,
+        })
+    }
+
+    /// Append the lines of a walked or failed repository, flushed at once so
+    /// that the file can be followed.
+    pub fn write(&mut self, (name, repo): &ScanItem) {
+        match repo {
+            Ok(repo) => self.write_records(records(repo)),
+            Err(failure) => self.write_failure(name, failure),
         }
     }
+
+    /// Append the line of a repository that failed, or of an account that
+    /// could not be listed.
+    pub fn write_failure(&mut self, name: &RepoName, failure: &Failure) {
+        self.write_records(vec![failure_record(name, failure)]);
+    }
+
+    fn write_records(&mut self, records: Vec<Value>) {
+        let written = records
+            .iter()
+            .try_for_each(|record| writeln!(self.file, "{}", record))
+            .and_then(|_| self.file.flush());
+
+        exit_on_error(written.map_err(Error::Io));
+    }
+
+    pub fn finish(self) {
+        println!("File -> {}", self.path.to_str().unwrap().yellow());
+    }
 }
 
-impl Exporter<User> {
-    pub fn export_user(self, data: &User) {
-        let output = self.global_config.output;
+/// Path of the output file, `<name>.<extension>` when the output is a folder,
+/// `None` when no output is set.
+fn output_path(global_config: &GlobalConfig, name: &str) -> Option<PathBuf> {
+    let output = &global_config.output;
+    if output.is_empty() {
+        return None;
+    }
 
-        if !output.is_empty() {
-            let mut path = PathBuf::from_str(&output).unwrap();
+    let path = PathBuf::from_str(output).unwrap();
+    if path.is_dir() {
+        return Some(path.join(format!("{}.{}", name, global_config.format.extension())));
+    }
 
-            if path.is_dir() {
-                path.set_file_name("user.json");
-            }
+    Some(path)
+}
 
-            let json_value = serde_json::to_string_pretty(data).unwrap();
-            fs::write(path.as_path(), json_value).unwrap();
+/// Rows sorted after a header.
+fn table(mut rows: Vec<Row>, separator: char, field: fn(&str) -> String) -> String {
+    rows.sort();
 
-            println!("File -> {}", path.to_str().unwrap().yellow());
+    let header = HEADER.map(str::to_string);
+    let mut table = String::new();
+    for row in [header].iter().chain(&rows) {
+        let fields = row.iter().map(|value| field(value)).collect::<Vec<_>>();
+        table.push_str(&fields.join(&separator.to_string()));
+        table.push('\n');
+    }
+
+    table
+}
+
+/// One row per branch, author and mail of `repository`.
+fn rows(repository: &Repository) -> Vec<Row> {
+    let mut rows = Vec::new();
+
+    for (branch, committers) in &repository.branch_data {
+        for (author, committer) in &committers.committers {
+            for (mail, entry) in &committer.mails {
+                rows.push([
+                    repository.full_name(),
+                    branch.to_string(),
+                    author.to_string(),
+                    mail.clone(),
+                    entry.commits.len().to_string(),
+                    entry.first_seen().map(format_datetime).unwrap_or_default(),
+                    entry.last_seen().map(format_datetime).unwrap_or_default(),
+                ]);
+            }
         }
     }
+
+    rows
 }
 
-impl Exporter<Org> {
-    pub fn export_org(self, data: &Org) {
-        let output = self.global_config.output;
+/// NDJSON records of `repository`, one per branch, author and mail, then
+/// one per tagger mail.
+fn records(repository: &Repository) -> Vec<Value> {
+    let mut records = Vec::new();
 
-        if !output.is_empty() {
-            let mut path = PathBuf::from_str(&output).unwrap();
+    for branch in repository.get_branches() {
+        let Some(committers) = repository.branch_data.get(branch) else {
+            continue;
+        };
+        let mut authors = committers.committers.iter().collect::<Vec<_>>();
+        authors.sort_by_key(|(author, _)| *author);
 
-            if path.is_dir() {
-                path.set_file_name("org.json");
+        for (author, committer) in authors {
+            for (mail, entry) in &committer.mails {
+                records.push(json!({
+                    "type": "commit",
+                    "repository": repository.full_name(),
+                    "owner": repository.owner,
+                    "branch": branch,
+                    "author": author,
+                    "mail": mail,
+                    "roles": entry.roles,
+                    "commits": entry.commits.len(),
+                    "first_seen": entry.first_seen().map(format_datetime),
+                    "last_seen": entry.last_seen().map(format_datetime),
+                }));
             }
+        }
+    }
 
-            let json_value = serde_json::to_string_pretty(data).unwrap();
-            fs::write(path.as_path(), json_value).unwrap();
+    let mut taggers = repository.tags.taggers.iter().collect::<Vec<_>>();
+    taggers.sort_by_key(|(author, _)| *author);
+    for (author, tagger) in taggers {
+        for (mail, tags) in &tagger.mails {
+            records.push(json!({
+                "type": "tag",
+                "repository": repository.full_name(),
+                "owner": repository.owner,
+                "author": author,
+                "mail": mail,
+                "tags": tags,
+            }));
+        }
+    }
 
-            println!("File -> {}", path.to_str().unwrap().yellow());
+    records
+}
+
+fn failure_record(name: &RepoName, failure: &Failure) -> Value {
+    json!({
+        "type": "failure",
+        "repository": name,
+        "stage": failure.stage,
+        "message": failure.message,
+    })
+}
+
+/// Quote the fields holding a separator, a quote or a line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// TSV has no quoting, tabs and line breaks become spaces.
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glit_core::{
+        config::{HttpConfig, TlsConfig},
+        types::Stage,
+    };
+    use std::{env, process};
+
+    /// Repository `acme/widget` with two mails of alice on `main`, one of bob
+    /// on `dev` and a tag of carol.
+    fn repository() -> Repository {
+        serde_json::from_value(json!({
+            "name": "widget",
+            "owner": "acme",
+            "empty": false,
+            "branches": ["main", "dev"],
+            "branch_data": {
+                "main": {"committers": {"alice": {"mails": {
+                    "alice@example.com": {"roles": ["author"], "commits": [
+                        {"id": "a1", "roles": ["author"], "time": 86_400},
+                        {"id": "a2", "roles": ["author"], "time": 0},
+                    ]},
+                    "alice@work.com": {"roles": ["committer"], "commits": [
+                        {"id": "a3", "roles": ["committer"], "time": 60},
+                    ]},
+                }}}},
+                "dev": {"committers": {"bob": {"mails": {
+                    "bob@example.com": {"roles": ["author"], "commits": [
+                        {"id": "b1", "roles": ["author"], "time": 3_600},
+                    ]},
+                }}}},
+            },
+            "tags": {"taggers": {"carol": {"mails": {"carol@example.com": ["v1.0"]}}}},
+        }))
+        .unwrap()
+    }
+
+    fn row(fields: [&str; 7]) -> Row {
+        fields.map(str::to_string)
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("alice"), "alice");
+        assert_eq!(csv_field("Doe, Jane"), "\"Doe, Jane\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_field("carriage\rreturn"), "\"carriage\rreturn\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn tsv_fields_have_no_tab_nor_line_break() {
+        assert_eq!(tsv_field("alice"), "alice");
+        assert_eq!(tsv_field("a\tb\nc\rd"), "a b c d");
+        assert_eq!(tsv_field("Doe, \"Jane\""), "Doe, \"Jane\"");
+    }
+
+    #[test]
+    fn table_sorts_the_rows_after_the_header() {
+        let rows = vec![
+            row(["b", "main", "bob", "bob@x", "1", "", ""]),
+            row(["a", "main", "Doe, Jane", "jane@x", "2", "", ""]),
+        ];
+
+        assert_eq!(
+            table(rows.clone(), ',', csv_field),
+            "repository,branch,author,mail,commits,first_seen,last_seen\n\
+             a,main,\"Doe, Jane\",jane@x,2,,\n\
+             b,main,bob,bob@x,1,,\n"
+        );
+        assert_eq!(
+            table(rows, '\t', tsv_field).lines().next().unwrap(),
+            HEADER.join("\t")
+        );
+    }
+
+    #[test]
+    fn rows_of_each_branch_author_and_mail() {
+        let mut rows = rows(&repository());
+        rows.sort();
+
+        assert_eq!(
+            rows,
+            [
+                row([
+                    "acme/widget",
+                    "dev",
+                    "bob",
+                    "bob@example.com",
+                    "1",
+                    "1970-01-01T01:00:00Z",
+                    "1970-01-01T01:00:00Z",
+                ]),
+                row([
+                    "acme/widget",
+                    "main",
+                    "alice",
+                    "alice@example.com",
+                    "2",
+                    "1970-01-01T00:00:00Z",
+                    "1970-01-02T00:00:00Z",
+                ]),
+                row([
+                    "acme/widget",
+                    "main",
+                    "alice",
+                    "alice@work.com",
+                    "1",
+                    "1970-01-01T00:01:00Z",
+                    "1970-01-01T00:01:00Z",
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_of_commits_then_tags() {
+        let records = records(&repository());
+        let lines = records
+            .iter()
+            .map(|record| {
+                (
+                    record["type"].as_str().unwrap(),
+                    record["branch"].as_str().unwrap_or_default(),
+                    record["mail"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Default branch first
+        assert_eq!(
+            lines,
+            [
+                ("commit", "main", "alice@example.com"),
+                ("commit", "main", "alice@work.com"),
+                ("commit", "dev", "bob@example.com"),
+                ("tag", "", "carol@example.com"),
+            ]
+        );
+        assert_eq!(records[0]["repository"], "acme/widget");
+        assert_eq!(records[0]["commits"], 2);
+        assert_eq!(records[0]["first_seen"], "1970-01-01T00:00:00Z");
+        assert_eq!(records[0]["roles"], json!(["author"]));
+        assert_eq!(records[3]["tags"], json!(["v1.0"]));
+
+        let failure = Failure {
+            stage: Stage::Listing,
+            message: "Not found".to_string(),
+        };
+        assert_eq!(
+            failure_record(&RepoName("acme".to_string()), &failure),
+            json!({
+                "type": "failure",
+                "repository": "acme",
+                "stage": "listing",
+                "message": "Not found",
+            })
+        );
+    }
+
+    #[test]
+    fn each_record_is_a_flushed_line() {
+        let path = env::temp_dir().join(format!("glit-ndjson-{}.ndjson", process::id()));
+        let global_config = GlobalConfig {
+            output: path.to_str().unwrap().to_string(),
+            format: ExportFormat::Ndjson,
+            verbose: false,
+            tls: TlsConfig::default(),
+            http: HttpConfig::default(),
+        };
+        let lines = || fs::read_to_string(&path).unwrap().lines().count();
+
+        let mut writer = NdjsonWriter::create(&global_config, "org").unwrap();
+        assert_eq!(lines(), 0);
+
+        let failure = Failure {
+            stage: Stage::Listing,
+            message: "Not found".to_string(),
+        };
+        writer.write_failure(&RepoName("acme".to_string()), &failure);
+        assert_eq!(lines(), 1);
+
+        writer.write(&(RepoName("acme/widget".to_string()), Ok(repository())));
+        assert_eq!(lines(), 5);
+
+        let content = fs::read_to_string(&path).unwrap();
+        for line in content.lines() {
+            serde_json::from_str::<Value>(line).unwrap();
         }
+
+        fs::remove_file(&path).unwrap();
     }
 }
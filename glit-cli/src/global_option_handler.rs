@@ -1,5 +1,7 @@
 use clap::ArgMatches;
-use glit_core::config::GlobalConfig;
+use glit_core::config::{ExportFormat, GlobalConfig, HttpConfig};
+
+use crate::utils::tls_config;
 
 pub struct GlobalOptionHandler();
 
@@ -15,6 +17,24 @@ impl GlobalOptionHandler {
             .unwrap_or(&"".to_string())
             .to_owned();
 
-        GlobalConfig { verbose, output }
+        let format = matches
+            .get_one::<ExportFormat>("format")
+            .copied()
+            .unwrap_or_default();
+
+        let tls = tls_config(matches);
+
+        let mut http = HttpConfig::default();
+        if let Some(max_requests) = matches.get_one::<usize>("max_requests") {
+            http.max_concurrent_requests = *max_requests;
+        }
+
+        GlobalConfig {
+            verbose,
+            output,
+            format,
+            tls,
+            http,
+        }
     }
 }
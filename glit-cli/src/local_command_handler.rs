@@ -0,0 +1,16 @@
+use clap::ArgMatches;
+use glit_core::config::RepositoryConfig;
+use std::{path::PathBuf, str::FromStr};
+
+pub struct LocalCommandHandler {}
+
+impl LocalCommandHandler {
+    pub fn config(subcommand_match: &ArgMatches) -> RepositoryConfig {
+        let repo_path = subcommand_match
+            .get_one::<String>("repo_path")
+            .unwrap()
+            .as_str();
+
+        RepositoryConfig::local(PathBuf::from_str(repo_path).unwrap())
+    }
+}
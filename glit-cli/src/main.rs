@@ -1,28 +1,39 @@
+pub mod cache_command_handler;
 pub mod exporter;
 pub mod global_option_handler;
+pub mod local_command_handler;
 pub mod org_command_handler;
 pub mod printer;
 pub mod repository_command_handler;
 pub mod user_command_handler;
 pub mod utils;
-use std::time::Instant;
+use std::{path::PathBuf, process, time::Instant};
 
-use clap::{crate_version, Arg, Command};
-use exporter::Exporter;
+use cache_command_handler::{CacheCommand, CacheCommandHandler};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    crate_version, value_parser, Arg, Command,
+};
+use exporter::{Exporter, NdjsonWriter};
 use glit_core::{
+    config::{CloneFilter, ExportFormat},
+    forge::ForgeKind,
+    http::HttpClient,
     org::{Org, OrgFactory},
-    repo::{Repository, RepositoryFactory},
+    repo::{remove_live_clones, Repository, RepositoryFactory},
     user::{User, UserFactory},
     Logger,
 };
 use global_option_handler::GlobalOptionHandler;
+use local_command_handler::LocalCommandHandler;
 use org_command_handler::OrgCommandHandler;
 use printer::Printer;
 use repository_command_handler::RepoCommandHandler;
-use reqwest::ClientBuilder;
+use reqwest::Url;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 use user_command_handler::UserCommandHandler;
+use utils::{exit_on_error, parse_visibility};
 
 #[tokio::main]
 async fn main() {
@@ -35,7 +46,7 @@ async fn main() {
             Arg::new("verbose")
                 .short('v')
                 .long("verbose")
-                .help("Add information on commit hash, username ...")
+                .help("Show commit hashes, dates, counts and roles of each mail")
                 .num_args(0),
         )
         .arg(
@@ -43,7 +54,97 @@ async fn main() {
                 .value_name("PATH")
                 .short('o')
                 .long("output")
-                .help("export data to json")
+                .help("export data to a file, or to <command>.<format> in a folder")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("format")
+                .value_name("FORMAT")
+                .long("format")
+                .help("Format of the output file (json, ndjson, csv, tsv) [default: json]")
+                .value_parser(value_parser!(ExportFormat))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("ca_cert")
+                .value_name("PATH")
+                .long("ca-cert")
+                .help("PEM certificate of an internal CA, trusted for API requests and clones")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("insecure")
+                .long("insecure")
+                .help("Do not verify TLS certificates")
+                .global(true)
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("max_requests")
+                .value_name("N")
+                .long("max-requests")
+                .help("Maximum number of simultaneous API requests [default: 8]")
+                .value_parser(value_parser!(usize))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("max_parallel_clones")
+                .value_name("N")
+                .long("max-parallel-clones")
+                .help("Maximum number of repositories cloned at the same time [default: 4]")
+                .value_parser(value_parser!(usize))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("max_parallel_walks")
+                .value_name("N")
+                .long("max-parallel-walks")
+                .help("Maximum number of clones walked at the same time [default: number of CPUs]")
+                .value_parser(value_parser!(usize))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("ssh_key")
+                .value_name("PATH")
+                .long("ssh-key")
+                .env("GLIT_SSH_KEY")
+                .help("Private key for SSH remotes, tried after the SSH agent")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("ssh_passphrase")
+                .value_name("PASSPHRASE")
+                .long("ssh-passphrase")
+                .env("GLIT_SSH_PASSPHRASE")
+                .hide_env_values(true)
+                .help("Passphrase of the SSH key")
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("workdir")
+                .value_name("PATH")
+                .long("workdir")
+                .help("Folder of the temporary clones [default: TMPDIR]")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("cache_dir")
+                .value_name("PATH")
+                .long("cache-dir")
+                .env("GLIT_CACHE_DIR")
+                .help("Keep bare mirrors in this folder, later scans only fetch the new commits")
+                .value_parser(value_parser!(PathBuf))
+                .global(true)
                 .num_args(1),
         )
         .subcommand(
@@ -62,25 +163,46 @@ async fn main() {
                         .long("all-branches")
                         .help("Get all branch of the repo")
                         .num_args(0),
+                )
+                .arg(
+                    Arg::new("filter")
+                        .value_name("FILTER")
+                        .long("filter")
+                        .help("Partial clone filter to only fetch commit metadata (blob:none, tree:0)")
+                        .value_parser(value_parser!(CloneFilter))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("token")
+                        .value_name("TOKEN")
+                        .long("token")
+                        .env("GLIT_TOKEN")
+                        .hide_env_values(true)
+                        .help("Token used to clone a private repository over HTTPS, or username:password (GITHUB_TOKEN is read for github.com)")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("local")
+                .about("Extract emails from all branches of a repository on disk, without network access")
+                .arg(
+                    Arg::new("repo_path")
+                        .value_name("PATH")
+                        .help("Path of a working tree or a bare repository")
+                        .required(true),
                 ),
         )
         .subcommand(
             Command::new("org")
-                .about("Extract emails from all repositories of a github organisation.")
+                .about("Extract emails from all repositories of an organisation, a group or a workspace.")
                 .arg(
                     Arg::new("org_url")
                         .value_name("URL")
                         .short('u')
                         .long("url")
-                        .help("Github url of an organisation."),
+                        .help("Url of an organisation, a (sub)group or a workspace."),
                 )
-                .arg(
-                    Arg::new("all_branches")
-                        .short('a')
-                        .long("all-branches")
-                        .help("Get all branch of the repo")
-                        .num_args(0),
-                ),
+                .args(account_args()),
         )
         .subcommand(
             Command::new("user")
@@ -90,20 +212,39 @@ async fn main() {
                         .value_name("URL")
                         .short('u')
                         .long("url")
-                        .help("Github url of a user"),
+                        .help("Url of a user"),
                 )
-                .arg(
-                    Arg::new("all_branches")
-                        .short('a')
-                        .long("all-branches")
-                        .help("Get all branch of the repo")
-                        .num_args(0),
-                ),
+                .args(account_args()),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manage the mirrors of the cache folder")
+                .subcommand(Command::new("list").about("List the cached mirrors (default)"))
+                .subcommand(
+                    Command::new("prune")
+                        .about("Delete the mirrors not fetched for some days")
+                        .arg(
+                            Arg::new("older_than")
+                                .value_name("DAYS")
+                                .long("older-than")
+                                .help("Age of the last fetch, in days")
+                                .value_parser(value_parser!(u64))
+                                .default_value("30")
+                                .num_args(1),
+                        ),
+                )
+                .subcommand(Command::new("clear").about("Delete every cached mirror")),
         )
         .get_matches();
 
-    let client = ClientBuilder::new().build().unwrap();
     let global_config = GlobalOptionHandler::config(&matches);
+    let client = exit_on_error(
+        global_config
+            .tls
+            .client_builder()
+            .and_then(|builder| Ok(builder.build()?)),
+    );
+    let client = HttpClient::with_config(client, global_config.http.clone());
 
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::INFO)
@@ -111,13 +252,37 @@ async fn main() {
 
     tracing::subscriber::set_global_default(subscriber).expect("Setting default subscriber failed");
 
+    // Clones in progress are not dropped on Ctrl-C, delete them before leaving
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            remove_live_clones();
+            process::exit(130);
+        }
+    });
+
     match matches.subcommand() {
         Some(("repo", sub_match)) => {
             let time = Instant::now();
             let repository_config = RepoCommandHandler::config(sub_match);
-            let repository: Repository = RepositoryFactory::with_config(repository_config).create();
+            let repository: Repository =
+                exit_on_error(RepositoryFactory::with_config(repository_config).create());
 
-            let repo_extraction = repository.extract_log();
+            let repo_extraction = exit_on_error(repository.extract_log());
+
+            let printer = Printer::new(global_config.clone());
+            printer.print_repo(&repo_extraction);
+
+            let exporter = Exporter::new(global_config);
+            exporter.export_repo(&repo_extraction);
+            println!("Done in {:?}", time.elapsed());
+        }
+        Some(("local", sub_match)) => {
+            let time = Instant::now();
+            let repository_config = LocalCommandHandler::config(sub_match);
+            let repository: Repository =
+                exit_on_error(RepositoryFactory::with_config(repository_config).create());
+
+            let repo_extraction = exit_on_error(repository.extract_log());
 
             let printer = Printer::new(global_config.clone());
             printer.print_repo(&repo_extraction);
@@ -129,11 +294,25 @@ async fn main() {
         Some(("user", sub_match)) => {
             let time = Instant::now();
             let user_config = UserCommandHandler::config(sub_match);
-            let user: User = UserFactory::with_config(user_config)
-                .build_with_client(&client)
-                .await;
+            let user_factory = exit_on_error(UserFactory::with_config(user_config));
+            let user: User = user_factory.build_with_client(&client).await;
 
-            let user_with_log = Logger::log_for(user, &client).await;
+            let mut ndjson = NdjsonWriter::create(&global_config, "user");
+            if let Some(writer) = ndjson.as_mut() {
+                // Accounts that could not be listed fail before the scan starts
+                user.failures
+                    .iter()
+                    .for_each(|failure| writer.write_failure(failure.key(), failure.value()));
+            }
+            let user_with_log = Logger::log_for_with(user, &client, &mut |item| {
+                if let Some(writer) = ndjson.as_mut() {
+                    writer.write(item);
+                }
+            })
+            .await;
+            if let Some(writer) = ndjson {
+                writer.finish();
+            }
 
             let printer = Printer::new(global_config.clone());
             printer.print_user(&user_with_log);
@@ -146,11 +325,25 @@ async fn main() {
             let time = Instant::now();
 
             let org_config = OrgCommandHandler::config(sub_match);
-            let org: Org = OrgFactory::with_config(org_config)
-                .build_with_client(&client)
-                .await;
+            let org_factory = exit_on_error(OrgFactory::with_config(org_config));
+            let org: Org = org_factory.build_with_client(&client).await;
 
-            let org_with_log = Logger::log_for(org, &client).await;
+            let mut ndjson = NdjsonWriter::create(&global_config, "org");
+            if let Some(writer) = ndjson.as_mut() {
+                // Accounts that could not be listed fail before the scan starts
+                org.failures
+                    .iter()
+                    .for_each(|failure| writer.write_failure(failure.key(), failure.value()));
+            }
+            let org_with_log = Logger::log_for_with(org, &client, &mut |item| {
+                if let Some(writer) = ndjson.as_mut() {
+                    writer.write(item);
+                }
+            })
+            .await;
+            if let Some(writer) = ndjson {
+                writer.finish();
+            }
 
             let printer = Printer::new(global_config.clone());
             printer.print_org(&org_with_log);
@@ -159,6 +352,79 @@ async fn main() {
             exporter.export_org(&org_with_log);
             println!("Done in {:?}", time.elapsed());
         }
+        Some(("cache", sub_match)) => {
+            let (cache, command) = CacheCommandHandler::config(sub_match);
+            let printer = Printer::new(global_config);
+
+            match command {
+                CacheCommand::List => printer.print_cache("Cache", &exit_on_error(cache.list())),
+                CacheCommand::Prune(older_than) => {
+                    printer.print_cache("Pruned", &exit_on_error(cache.prune(older_than)))
+                }
+                CacheCommand::Clear => {
+                    printer.print_cache("Cleared", &exit_on_error(cache.clear()))
+                }
+            }
+        }
         _ => {}
     }
 }
+
+/// Arguments of the `org` and `user` subcommands besides their url.
+fn account_args() -> [Arg; 8] {
+    [
+        Arg::new("all_branches")
+            .short('a')
+            .long("all-branches")
+            .help("Get all branch of the repo")
+            .num_args(0),
+        Arg::new("filter")
+            .value_name("FILTER")
+            .long("filter")
+            .help("Partial clone filter to only fetch commit metadata (blob:none, tree:0)")
+            .value_parser(value_parser!(CloneFilter))
+            .num_args(1),
+        Arg::new("token")
+            .value_name("TOKEN")
+            .long("token")
+            .env("GLIT_TOKEN")
+            .hide_env_values(true)
+            .help("Token used to list and clone repositories, or username:password (GITHUB_TOKEN is read for github.com)")
+            .num_args(1),
+        Arg::new("forge")
+            .value_name("FORGE")
+            .long("forge")
+            .help("Git hosting service of the url (github, gitlab, gitea, bitbucket), detected from the host by default")
+            .value_parser(value_parser!(ForgeKind))
+            .num_args(1),
+        Arg::new("api_url")
+            .value_name("URL")
+            .long("api-url")
+            .help("Base url of the API, e.g. https://github.example.com/api/v3 for GitHub Enterprise Server")
+            .value_parser(value_parser!(Url))
+            .num_args(1),
+        Arg::new("visibility")
+            .value_name("VISIBILITY")
+            .long("visibility")
+            .help("Only scan repositories with this visibility, private and internal ones need a token")
+            .value_parser(
+                PossibleValuesParser::new(["public", "private", "internal", "all"])
+                    .try_map(parse_visibility),
+            )
+            .default_value("all")
+            .num_args(1),
+        Arg::new("checkpoint")
+            .value_name("STATE")
+            .long("checkpoint")
+            .help("Save each walked repository to this state file, to resume an interrupted scan")
+            .value_parser(value_parser!(PathBuf))
+            .num_args(1),
+        Arg::new("resume")
+            .value_name("STATE")
+            .long("resume")
+            .help("Skip the repositories saved in this state file, and save the next ones to it")
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with("checkpoint")
+            .num_args(1),
+    ]
+}
@@ -1,8 +1,12 @@
 use clap::ArgMatches;
-use glit_core::config::OrgConfig;
+use glit_core::config::{CloneFilter, OrgConfig};
+use glit_core::forge::{ForgeKind, Visibility};
 use reqwest::Url;
 
-use crate::utils::fix_input_url;
+use crate::utils::{
+    cache_dir, checkpoint_config, concurrency_config, credentials_config, fix_input_url,
+    tls_config, workdir,
+};
 
 pub struct OrgCommandHandler {}
 
@@ -18,11 +22,44 @@ impl OrgCommandHandler {
             .unwrap()
             .to_owned();
 
-        let org_url = fix_input_url(org_url);
+        let filter = subcommand_match.get_one::<CloneFilter>("filter").copied();
+
+        let forge = subcommand_match.get_one::<ForgeKind>("forge").copied();
+
+        let api_url = subcommand_match.get_one::<Url>("api_url").cloned();
+
+        let visibility = subcommand_match
+            .get_one::<Option<Visibility>>("visibility")
+            .copied()
+            .flatten();
+
+        let concurrency = concurrency_config(subcommand_match);
+
+        let tls = tls_config(subcommand_match);
+
+        let workdir = workdir(subcommand_match);
+
+        let cache = cache_dir(subcommand_match);
+
+        let checkpoint = checkpoint_config(subcommand_match);
+
+        let org_url = Url::parse(&fix_input_url(org_url)).unwrap();
+
+        let credentials = credentials_config(subcommand_match, &org_url);
 
         OrgConfig {
-            url: Url::parse(&org_url).unwrap(),
+            url: org_url,
             all_branches,
+            filter,
+            credentials,
+            forge,
+            api_url,
+            visibility,
+            concurrency,
+            tls,
+            workdir,
+            cache,
+            checkpoint,
         }
     }
 }
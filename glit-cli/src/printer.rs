@@ -1,6 +1,16 @@
 use colored::Colorize;
-use glit_core::{config::GlobalConfig, org::Org, repo::Repository, user::User};
-use std::marker::PhantomData;
+use glit_core::{
+    cache::{Cache, CachedMirror},
+    config::GlobalConfig,
+    org::Org,
+    repo::{MailEntry, Repository},
+    types::RepoName,
+    user::User,
+    Failure,
+};
+use std::{cmp::Reverse, marker::PhantomData};
+
+use crate::utils::format_date;
 
 pub struct Printer<T> {
     global_config: GlobalConfig,
@@ -11,17 +21,60 @@ impl<T> Printer<T> {
     pub fn new(global_config: GlobalConfig) -> Self {
         Self {
             global_config,
-            data: PhantomData::default(),
+            data: PhantomData,
         }
     }
 }
 
 impl Printer<Repository> {
     pub fn print_repo(&self, data: &Repository) {
+        if data.empty {
+            println!("{}", "[ Empty repository ]".yellow());
+            println!();
+            return;
+        }
+
         if self.global_config.verbose {
+            let mut branches = data.branch_data.iter().collect::<Vec<_>>();
+            branches
+                .sort_by_key(|(branch, _)| data.get_branches().iter().position(|b| b == *branch));
+
+            for (branch, value) in branches {
+                let branch_format = format!("[ Branch : {} ]", branch).yellow();
+                println!("{}", branch_format);
+
+                let mut committers = value.committers.iter().collect::<Vec<_>>();
+                committers.sort_by_key(|(author, _)| *author);
+                for (author, committer) in committers {
+                    println!("{}", author.to_string().trim().blue());
+                    for (mail, entry) in &committer.mails {
+                        print_mail_entry(mail, entry);
+                    }
+                }
+                println!();
+            }
+
+            if !data.tags.is_empty() {
+                println!("{}", "[ Tags ]".yellow());
+                let mut taggers = data.tags.taggers.iter().collect::<Vec<_>>();
+                taggers.sort_by_key(|(tagger, _)| *tagger);
+                for (tagger, value) in taggers {
+                    println!("{}", tagger.to_string().trim().blue());
+                    for (mail, tags) in &value.mails {
+                        let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+                        println!(
+                            "  {} {} : {}",
+                            format_mail(mail.trim()),
+                            count(tags.len(), "tag"),
+                            truncate(&tags, MAX_VERBOSE_ITEMS)
+                        );
+                    }
+                }
+                println!();
+            }
         } else {
             for (branch, value) in &data.branch_data {
-                let branch_format = format!("[ Branch : {} ]", branch.to_string()).yellow();
+                let branch_format = format!("[ Branch : {} ]", branch).yellow();
                 println!("{}", branch_format);
                 for (author, data) in &value.committers {
                     let mails = data.mails.keys().cloned().collect::<Vec<String>>();
@@ -33,6 +86,19 @@ impl Printer<Repository> {
                 }
                 println!();
             }
+
+            if !data.tags.is_empty() {
+                println!("{}", "[ Tags ]".yellow());
+                for (tagger, data) in &data.tags.taggers {
+                    let mails = data.mails.keys().cloned().collect::<Vec<String>>();
+                    print!("{}:", tagger.to_string().trim().blue());
+
+                    print_mail(mails, tagger.to_string().trim());
+
+                    println!();
+                }
+                println!();
+            }
         }
     }
 }
@@ -41,20 +107,169 @@ impl Printer<User> {
     pub fn print_user(&self, data: &User) {
         let printer = Printer::new(self.global_config.clone());
         for (repo_name, value) in data.repositories_data.clone() {
-            let repo_format = format!("[ Repository : {} ]", repo_name.to_string()).magenta();
+            let repo_format = format!("[ Repository : {} ]", repo_name).magenta();
             println!("{}", repo_format);
             printer.print_repo(&value);
         }
+
+        print_failures(
+            data.failures
+                .iter()
+                .map(|f| (f.key().clone(), f.value().clone())),
+        );
     }
 }
 impl Printer<Org> {
     pub fn print_org(&self, data: &Org) {
         let printer = Printer::new(self.global_config.clone());
         for (repo_name, value) in data.repositories_data.clone() {
-            let repo_format = format!("[ Repository : {} ]", repo_name.to_string()).magenta();
+            let repo_format = format!("[ Repository : {} ]", repo_name).magenta();
             println!("{}", repo_format);
             printer.print_repo(&value);
         }
+
+        print_failures(
+            data.failures
+                .iter()
+                .map(|f| (f.key().clone(), f.value().clone())),
+        );
+    }
+}
+
+impl Printer<Cache> {
+    /// Mirrors listed, or deleted by a prune or a clear, with `title`.
+    pub fn print_cache(&self, title: &str, mirrors: &[CachedMirror]) {
+        let size = mirrors.iter().map(|mirror| mirror.size).sum();
+        let summary = format!(
+            "[ {} : {} mirrors, {} ]",
+            title,
+            mirrors.len(),
+            format_size(size)
+        );
+        println!("{}", summary.yellow());
+
+        for mirror in mirrors {
+            let url = mirror.url.as_deref().unwrap_or("unreadable mirror");
+            let age = match mirror.age() {
+                Some(age) => format!("fetched {} ago", format_age(age.as_secs())),
+                None => "never fetched".to_string(),
+            };
+            println!(
+                "{} {} ({}, {})",
+                url.blue(),
+                mirror.path.display(),
+                format_size(mirror.size),
+                age
+            );
+        }
+        println!();
+    }
+}
+
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 * 60 => format!("{} min", s / 60),
+        s if s < 24 * 60 * 60 => format!("{} h", s / (60 * 60)),
+        s => format!("{} days", s / (24 * 60 * 60)),
+    }
+}
+
+/// Summary of the repositories left out of the result, by name.
+fn print_failures(failures: impl Iterator<Item = (RepoName, Failure)>) {
+    let mut failures = failures.collect::<Vec<_>>();
+    if failures.is_empty() {
+        return;
+    }
+    failures.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+    let summary = format!("[ Failures : {} ]", failures.len()).red();
+    println!("{}", summary);
+    for (repo_name, failure) in failures {
+        println!(
+            "{} ({}): {}",
+            repo_name.to_string().blue(),
+            failure.stage,
+            failure.message
+        );
+    }
+    println!();
+}
+
+/// Commits and tags listed per mail in verbose mode, the others are counted.
+const MAX_VERBOSE_ITEMS: usize = 10;
+
+/// Length of the abbreviated commit hashes.
+const SHORT_HASH_LEN: usize = 7;
+
+/// Verbose line of a mail: commit count, dates, roles, then the latest hashes.
+fn print_mail_entry(mail: &str, entry: &MailEntry) {
+    let dates = match (entry.first_seen(), entry.last_seen()) {
+        (Some(first), Some(last)) => format!("{} -> {}", format_date(first), format_date(last)),
+        _ => "-".to_string(),
+    };
+    let roles = entry
+        .roles
+        .iter()
+        .map(|role| role.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    println!(
+        "  {} {}, {} ({})",
+        format_mail(mail.trim()),
+        count(entry.commits.len(), "commit"),
+        dates,
+        roles
+    );
+
+    println!(
+        "    {}",
+        truncate(&latest_hashes(entry), MAX_VERBOSE_ITEMS).dimmed()
+    );
+}
+
+/// Abbreviated hashes of the commits of `entry`, newest first.
+fn latest_hashes(entry: &MailEntry) -> Vec<String> {
+    let mut commits = entry.commits.iter().collect::<Vec<_>>();
+    commits.sort_by_key(|commit| Reverse(commit.time));
+    commits
+        .iter()
+        .map(|commit| commit.id.chars().take(SHORT_HASH_LEN).collect::<String>())
+        .collect()
+}
+
+fn count(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        count => format!("{} {}s", count, noun),
+    }
+}
+
+/// The first `max` items, and the count of the others.
+fn truncate(items: &[String], max: usize) -> String {
+    let shown = items
+        .iter()
+        .take(max)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    match items.len().saturating_sub(max) {
+        0 => shown,
+        more => format!("{} ... (+{} more)", shown, more),
     }
 }
 
@@ -84,3 +299,40 @@ fn format_mail(mail: &str) -> String {
         mail.green().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glit_core::{repo::Commit, types::Role};
+
+    fn items(count: usize) -> Vec<String> {
+        (1..=count).map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn truncate_counts_the_hidden_items() {
+        assert_eq!(truncate(&[], MAX_VERBOSE_ITEMS), "");
+        assert_eq!(truncate(&items(3), MAX_VERBOSE_ITEMS), "1 2 3");
+        assert_eq!(
+            truncate(&items(MAX_VERBOSE_ITEMS), MAX_VERBOSE_ITEMS),
+            "1 2 3 4 5 6 7 8 9 10"
+        );
+        assert_eq!(
+            truncate(&items(MAX_VERBOSE_ITEMS + 2), MAX_VERBOSE_ITEMS),
+            "1 2 3 4 5 6 7 8 9 10 ... (+2 more)"
+        );
+    }
+
+    #[test]
+    fn latest_hashes_are_abbreviated_newest_first() {
+        let mut entry = MailEntry::new("a".repeat(40), Role::Author, 100);
+        entry
+            .commits
+            .push(Commit::new("b".repeat(40), Role::Author, 300));
+        entry
+            .commits
+            .push(Commit::new("c123".to_string(), Role::Author, 200));
+
+        assert_eq!(latest_hashes(&entry), ["bbbbbbb", "c123", "aaaaaaa"]);
+    }
+}
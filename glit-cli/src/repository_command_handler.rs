@@ -1,8 +1,8 @@
 use clap::ArgMatches;
-use glit_core::config::RepositoryConfig;
+use glit_core::config::{CloneFilter, RepositoryConfig};
 use reqwest::Url;
 
-use crate::utils::fix_input_url;
+use crate::utils::{cache_dir, credentials_config, fix_input_url, tls_config, workdir};
 
 pub struct RepoCommandHandler {}
 
@@ -18,13 +18,28 @@ impl RepoCommandHandler {
             .unwrap()
             .to_owned();
 
-        let repository_url = fix_input_url(repo_url);
+        let filter = subcommand_match.get_one::<CloneFilter>("filter").copied();
+
+        let tls = tls_config(subcommand_match);
+
+        let workdir = workdir(subcommand_match);
+
+        let cache = cache_dir(subcommand_match);
+
+        let repository_url = Url::parse(&fix_input_url(repo_url)).unwrap();
+
+        let credentials = credentials_config(subcommand_match, &repository_url);
 
         // Fail fast -> Check repository and branch existence
 
-        RepositoryConfig {
-            url: Url::parse(&repository_url).unwrap(),
+        RepositoryConfig::new(
+            repository_url,
             all_branches,
-        }
+            filter,
+            tls,
+            credentials,
+            workdir,
+            cache,
+        )
     }
 }
@@ -1,8 +1,12 @@
 use clap::ArgMatches;
-use glit_core::config::UserConfig;
+use glit_core::config::{CloneFilter, UserConfig};
+use glit_core::forge::{ForgeKind, Visibility};
 use reqwest::Url;
 
-use crate::utils::fix_input_url;
+use crate::utils::{
+    cache_dir, checkpoint_config, concurrency_config, credentials_config, fix_input_url,
+    tls_config, workdir,
+};
 
 pub struct UserCommandHandler {}
 
@@ -18,11 +22,44 @@ impl UserCommandHandler {
             .unwrap()
             .to_owned();
 
-        let user_url = fix_input_url(user_url);
+        let filter = subcommand_match.get_one::<CloneFilter>("filter").copied();
+
+        let forge = subcommand_match.get_one::<ForgeKind>("forge").copied();
+
+        let api_url = subcommand_match.get_one::<Url>("api_url").cloned();
+
+        let visibility = subcommand_match
+            .get_one::<Option<Visibility>>("visibility")
+            .copied()
+            .flatten();
+
+        let concurrency = concurrency_config(subcommand_match);
+
+        let tls = tls_config(subcommand_match);
+
+        let workdir = workdir(subcommand_match);
+
+        let cache = cache_dir(subcommand_match);
+
+        let checkpoint = checkpoint_config(subcommand_match);
+
+        let user_url = Url::parse(&fix_input_url(user_url)).unwrap();
+
+        let credentials = credentials_config(subcommand_match, &user_url);
 
         UserConfig {
-            url: Url::parse(&user_url).unwrap(),
+            url: user_url,
             all_branches,
+            filter,
+            credentials,
+            forge,
+            api_url,
+            visibility,
+            concurrency,
+            tls,
+            workdir,
+            cache,
+            checkpoint,
         }
     }
 }
@@ -1,3 +1,13 @@
+use clap::ArgMatches;
+use colored::Colorize;
+use glit_core::{
+    config::{CheckpointConfig, ConcurrencyConfig, CredentialsConfig, TlsConfig},
+    forge::Visibility,
+    Error,
+};
+use reqwest::Url;
+use std::{env, path::PathBuf, process};
+
 pub fn fix_input_url(input_url: &str) -> String {
     let mut url = String::new();
     if !&input_url.ends_with('/') {
@@ -8,3 +18,218 @@ pub fn fix_input_url(input_url: &str) -> String {
 
     input_url.to_string()
 }
+
+/// TLS options are global, they are also found in the matches of every subcommand.
+pub fn tls_config(matches: &ArgMatches) -> TlsConfig {
+    let ca_cert = matches.get_one::<PathBuf>("ca_cert").cloned();
+
+    let insecure = matches
+        .get_one::<bool>("insecure")
+        .unwrap_or(&false)
+        .to_owned();
+
+    TlsConfig { ca_cert, insecure }
+}
+
+/// Concurrency options are global, unset ones keep their default.
+pub fn concurrency_config(matches: &ArgMatches) -> ConcurrencyConfig {
+    let mut concurrency = ConcurrencyConfig::default();
+
+    if let Some(max_parallel_clones) = matches.get_one::<usize>("max_parallel_clones") {
+        concurrency.max_parallel_clones = *max_parallel_clones;
+    }
+    if let Some(max_parallel_walks) = matches.get_one::<usize>("max_parallel_walks") {
+        concurrency.max_parallel_walks = *max_parallel_walks;
+    }
+
+    concurrency
+}
+
+/// Folder of the temporary clones, `TMPDIR` by default.
+pub fn workdir(matches: &ArgMatches) -> PathBuf {
+    matches
+        .get_one::<PathBuf>("workdir")
+        .cloned()
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Folder of the cached mirrors, clones are temporary when not set.
+pub fn cache_dir(matches: &ArgMatches) -> Option<PathBuf> {
+    matches.get_one::<PathBuf>("cache_dir").cloned()
+}
+
+/// State file of an org or user scan, `--resume` continues the scan saved in it.
+pub fn checkpoint_config(matches: &ArgMatches) -> Option<CheckpointConfig> {
+    if let Some(path) = matches.get_one::<PathBuf>("resume") {
+        return Some(CheckpointConfig {
+            path: path.clone(),
+            resume: true,
+        });
+    }
+
+    matches
+        .get_one::<PathBuf>("checkpoint")
+        .map(|path| CheckpointConfig {
+            path: path.clone(),
+            resume: false,
+        })
+}
+
+/// Only for the subcommands with a `token` argument, the SSH options are global.
+/// `GITHUB_TOKEN` is only read for a github.com `url` without `--api-url`, so
+/// that a GitHub token is never sent to another host.
+pub fn credentials_config(matches: &ArgMatches, url: &Url) -> CredentialsConfig {
+    let api_url = matches.try_get_one::<Url>("api_url").ok().flatten();
+    let token = matches.get_one::<String>("token").cloned().or_else(|| {
+        (url.host_str() == Some("github.com") && api_url.is_none())
+            .then(|| env::var("GITHUB_TOKEN").ok())
+            .flatten()
+            .filter(|token| !token.is_empty())
+    });
+    let ssh_key = matches.get_one::<PathBuf>("ssh_key").cloned();
+    let ssh_passphrase = matches.get_one::<String>("ssh_passphrase").cloned();
+
+    CredentialsConfig {
+        token,
+        username: None,
+        ssh_key,
+        ssh_passphrase,
+    }
+}
+
+/// `--visibility` value, `all` being no filter.
+pub fn parse_visibility(value: String) -> Result<Option<Visibility>, String> {
+    match value.as_str() {
+        "all" => Ok(None),
+        visibility => visibility.parse().map(Some),
+    }
+}
+
+/// `YYYY-MM-DD` date in UTC of a git time in seconds since the epoch.
+pub fn format_date(time: i64) -> String {
+    // Days to civil date, from Howard Hinnant's `civil_from_days`
+    let days = time.div_euclid(24 * 60 * 60) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` date and time in UTC of a git time in seconds since the epoch.
+pub fn format_datetime(time: i64) -> String {
+    let seconds = time.rem_euclid(24 * 60 * 60);
+
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(time),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Print the error and stop, for the failures that leave nothing to report.
+pub fn exit_on_error<T>(result: Result<T, Error>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{} {}", "Error :".red().bold(), e);
+        process::exit(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{value_parser, Arg, Command};
+
+    /// Matches of a subcommand with the credentials and `--api-url` arguments.
+    fn matches(args: &[&str]) -> ArgMatches {
+        Command::new("org")
+            .arg(Arg::new("token").long("token"))
+            .arg(
+                Arg::new("ssh_key")
+                    .long("ssh-key")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(Arg::new("ssh_passphrase").long("ssh-passphrase"))
+            .arg(
+                Arg::new("api_url")
+                    .long("api-url")
+                    .value_parser(value_parser!(Url)),
+            )
+            .get_matches_from([&["org"], args].concat())
+    }
+
+    #[test]
+    fn all_visibilities_is_no_filter() {
+        let visibility = |value: &str| parse_visibility(value.to_string());
+
+        assert_eq!(visibility("all"), Ok(None));
+        assert_eq!(visibility("public"), Ok(Some(Visibility::Public)));
+        assert_eq!(visibility("internal"), Ok(Some(Visibility::Internal)));
+        assert!(visibility("unknown").is_err());
+        assert!(visibility("privat").is_err());
+    }
+
+    #[test]
+    fn github_token_is_only_read_for_github_com() {
+        let token = |args: &[&str], url: &str| {
+            credentials_config(&matches(args), &Url::parse(url).unwrap()).token
+        };
+        env::set_var("GITHUB_TOKEN", "ghp_secret");
+
+        assert_eq!(
+            token(&[], "https://github.com/rust"),
+            Some("ghp_secret".to_string())
+        );
+        assert_eq!(token(&[], "https://github.example.com/rust"), None);
+        assert_eq!(token(&[], "https://gitlab.com/gitlab-org"), None);
+        assert_eq!(
+            token(
+                &["--api-url", "https://proxy.example.com/"],
+                "https://github.com/rust"
+            ),
+            None
+        );
+        // A token given on purpose is sent to any host
+        assert_eq!(
+            token(&["--token", "glpat"], "https://gitlab.com/gitlab-org"),
+            Some("glpat".to_string())
+        );
+
+        env::set_var("GITHUB_TOKEN", "");
+        assert_eq!(token(&[], "https://github.com/rust"), None);
+        env::remove_var("GITHUB_TOKEN");
+    }
+
+    #[test]
+    fn dates_around_the_epoch() {
+        assert_eq!(format_datetime(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_datetime(-1), "1969-12-31T23:59:59Z");
+        assert_eq!(format_datetime(-2_208_988_800), "1900-01-01T00:00:00Z");
+        assert_eq!(format_date(86_399), "1970-01-01");
+        assert_eq!(format_date(86_400), "1970-01-02");
+    }
+
+    #[test]
+    fn dates_around_leap_days() {
+        // 2000 is a leap year, as a multiple of 400
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(951_868_800), "2000-03-01");
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+        // 2100 is not, as a multiple of 100
+        assert_eq!(format_date(4_107_456_000), "2100-02-28");
+        assert_eq!(format_date(4_107_542_400), "2100-03-01");
+        assert_eq!(format_datetime(253_402_300_799), "9999-12-31T23:59:59Z");
+    }
+}
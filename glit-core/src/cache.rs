@@ -0,0 +1,191 @@
+use reqwest::Url;
+use serde::Serialize;
+use std::{
+    fs::{self, remove_dir_all},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+use crate::{error::Error, repo::allow_partial_clone};
+
+/// File written in a mirror after each clone or fetch, with the time in seconds.
+const LAST_FETCH: &str = "glit-last-fetch";
+
+/// Folder of bare mirrors kept between scans, so that a repository already
+/// scanned only fetches its new objects.
+///
+/// Mirrors are stored at `<root>/<host>/<path>.git`, after the remote url.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+/// A mirror found in the cache.
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedMirror {
+    /// Url of the `origin` remote, `None` when the mirror can not be opened
+    pub url: Option<String>,
+    pub path: PathBuf,
+    /// Size on disk in bytes
+    pub size: u64,
+    /// Time of the last clone or fetch, in seconds since the epoch
+    pub last_fetch: Option<u64>,
+}
+
+impl CachedMirror {
+    /// Time since the last clone or fetch, `None` when unknown.
+    pub fn age(&self) -> Option<Duration> {
+        let last_fetch = UNIX_EPOCH + Duration::from_secs(self.last_fetch?);
+        SystemTime::now().duration_since(last_fetch).ok()
+    }
+}
+
+impl Cache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Location of the mirror of `url`. Credentials and query of the url are left out.
+    pub fn mirror_path(&self, url: &Url) -> PathBuf {
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}_{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => "local".to_string(),
+        };
+
+        let mut path = self.root.join(host);
+        let segments = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+            .map(|segment| segment.trim_end_matches(".git"));
+        for segment in segments {
+            path.push(segment);
+        }
+
+        // Names may hold dots, the extension is appended rather than set
+        let mut mirror = path.into_os_string();
+        mirror.push(".git");
+        PathBuf::from(mirror)
+    }
+
+    /// Every mirror of the cache, an empty list when the cache does not exist yet.
+    pub fn list(&self) -> Result<Vec<CachedMirror>, Error> {
+        // Mirrors may come from a partial clone
+        allow_partial_clone();
+
+        let mut mirrors = Vec::new();
+        if self.root.exists() {
+            find_mirrors(&self.root, &mut mirrors)?;
+        }
+        mirrors.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(mirrors)
+    }
+
+    /// Delete the mirrors not fetched for `older_than`, and the ones that can
+    /// not be opened anymore.
+    pub fn prune(&self, older_than: Duration) -> Result<Vec<CachedMirror>, Error> {
+        let pruned = self
+            .list()?
+            .into_iter()
+            .filter(|mirror| {
+                mirror.url.is_none() || mirror.age().is_none_or(|age| age > older_than)
+            })
+            .collect::<Vec<_>>();
+
+        for mirror in &pruned {
+            remove_mirror(&mirror.path)?;
+        }
+
+        Ok(pruned)
+    }
+
+    /// Delete every mirror, other files of the cache folder are left alone.
+    pub fn clear(&self) -> Result<Vec<CachedMirror>, Error> {
+        let mirrors = self.list()?;
+        for mirror in &mirrors {
+            remove_mirror(&mirror.path)?;
+        }
+
+        Ok(mirrors)
+    }
+
+    /// Record a clone or a fetch of the mirror at `path`.
+    pub(crate) fn touch(path: &Path) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        fs::write(path.join(LAST_FETCH), now.to_string())
+    }
+}
+
+/// A bare repository holds a `HEAD` file and an `objects` folder, other folders
+/// are hosts or owners.
+fn find_mirrors(path: &Path, mirrors: &mut Vec<CachedMirror>) -> io::Result<()> {
+    if path.join("HEAD").is_file() && path.join("objects").is_dir() {
+        mirrors.push(mirror(path)?);
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            find_mirrors(&entry.path(), mirrors)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn mirror(path: &Path) -> io::Result<CachedMirror> {
+    let url = git2::Repository::open_bare(path)
+        .and_then(|repo| {
+            repo.find_remote("origin")
+                .map(|r| r.url().map(str::to_string))
+        })
+        .ok()
+        .flatten();
+
+    let last_fetch = fs::read_to_string(path.join(LAST_FETCH))
+        .ok()
+        .and_then(|last_fetch| last_fetch.trim().parse().ok());
+
+    Ok(CachedMirror {
+        url,
+        path: path.to_path_buf(),
+        size: dir_size(path)?,
+        last_fetch,
+    })
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+fn remove_mirror(path: &Path) -> io::Result<()> {
+    remove_dir_all(path)?;
+    info!("Cleaning - Delete mirror at {:?}", path);
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use ahash::HashMap;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+use tracing::{info, warn};
+
+use crate::{config::CheckpointConfig, error::Error, repo::Repository};
+
+/// State file of an org or user scan, with one JSON line per repository
+/// walked, so that an interrupted scan can be resumed.
+///
+/// Failed repositories are not recorded, a resumed scan tries them again.
+#[derive(Debug)]
+pub struct Checkpoint {
+    file: Mutex<File>,
+    /// Repositories found in the state file, by url
+    done: HashMap<String, Repository>,
+}
+
+/// Line of the state file, written from references and read as owned values.
+#[derive(Serialize, Deserialize)]
+struct Entry<U, R> {
+    url: U,
+    repository: R,
+}
+
+impl Checkpoint {
+    pub fn with_config(checkpoint_config: &CheckpointConfig) -> Result<Self, Error> {
+        if checkpoint_config.resume {
+            Self::resume(&checkpoint_config.path)
+        } else {
+            Self::create(&checkpoint_config.path)
+        }
+    }
+
+    /// Start a new state file at `path`, replacing an existing one.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        let file = File::create(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            done: HashMap::default(),
+        })
+    }
+
+    /// Load the repositories of the state file at `path`, then append the next
+    /// ones to it. A missing file starts a new scan.
+    pub fn resume(path: &Path) -> Result<Self, Error> {
+        let mut done = HashMap::default();
+
+        let state = if path.exists() {
+            fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        for (number, line) in state.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // The last line is cut when the process was killed while writing it
+            match serde_json::from_str::<Entry<String, Repository>>(line) {
+                Ok(entry) => {
+                    done.insert(entry.url, entry.repository);
+                }
+                Err(e) => warn!("{:?}:{} skipped : {}", path, number + 1, e),
+            }
+        }
+        info!("{} repositories already done in {:?}", done.len(), path);
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if !state.is_empty() && !state.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Self {
+            file: Mutex::new(file),
+            done,
+        })
+    }
+
+    /// Repository already walked by a previous run.
+    pub fn get(&self, url: &Url) -> Option<&Repository> {
+        self.done.get(url.as_str())
+    }
+
+    /// Append a walked repository, the line is written at once.
+    pub fn record(&self, url: &Url, repository: &Repository) -> Result<(), Error> {
+        let entry = Entry {
+            url: url.as_str(),
+            repository,
+        };
+        let mut line = serde_json::to_string(&entry).map_err(|e| Error::Parse(e.to_string()))?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
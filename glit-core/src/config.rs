@@ -1,31 +1,316 @@
-use reqwest::Url;
+use reqwest::{Certificate, ClientBuilder, Url};
+use std::{env, fmt, fs, path::PathBuf, str::FromStr, thread, time::Duration};
+
+use crate::{
+    error::Error,
+    forge::{ForgeKind, Visibility},
+};
 
 #[derive(Debug, Clone)]
 pub struct GlobalConfig {
     pub output: String,
+    pub format: ExportFormat,
     pub verbose: bool,
+    pub tls: TlsConfig,
+    pub http: HttpConfig,
+}
+
+/// Format of the file written with `--output`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The whole result as a single document
+    #[default]
+    Json,
+    /// One JSON object per line and identity, written as repositories are walked
+    Ndjson,
+    /// One row per repository, branch, author and mail
+    Csv,
+    /// Same rows as `Csv`, separated by tabs
+    Tsv,
+}
+
+impl ExportFormat {
+    /// Extension of the file written in an output folder.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            "tsv" => Ok(ExportFormat::Tsv),
+            _ => Err(format!("Unsupported export format : {}", s)),
+        }
+    }
+}
+
+/// TLS settings for self-hosted instances served with an internal CA, used by
+/// both the API client and the clones.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// PEM certificate trusted in addition to the system ones
+    pub ca_cert: Option<PathBuf>,
+    /// Skip certificate verification altogether
+    pub insecure: bool,
+}
+
+impl TlsConfig {
+    pub fn client_builder(&self) -> Result<ClientBuilder, Error> {
+        let mut builder = ClientBuilder::new().danger_accept_invalid_certs(self.insecure);
+
+        if let Some(ca_cert) = &self.ca_cert {
+            let pem = fs::read(ca_cert)?;
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Limits of an org or user scan, so that it does not saturate the bandwidth
+/// or the disk of the host.
+#[derive(Debug, Clone, Copy)]
+pub struct ConcurrencyConfig {
+    /// Repositories cloned at the same time
+    pub max_parallel_clones: usize,
+    /// Clones walked at the same time, finished clones wait for a free walker
+    pub max_parallel_walks: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+
+        Self {
+            max_parallel_clones: 4,
+            max_parallel_walks: cpus,
+        }
+    }
+}
+
+/// Limits of the HTTP requests made to list repositories.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub max_concurrent_requests: usize,
+    /// Retries of a rate limited or failed request
+    pub max_retries: u32,
+    /// First backoff delay, doubled on each retry
+    pub base_delay: Duration,
+    /// Longest wait for a rate limit reset, the request fails beyond
+    pub max_wait: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 8,
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_wait: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// Credentials for private repositories. The token is used for the API requests
+/// and, as HTTPS password, for the clones.
+#[derive(Clone, Default)]
+pub struct CredentialsConfig {
+    /// API token, or `username:password`
+    pub token: Option<String>,
+    /// Username sent with the token over HTTPS, derived from the forge when not set
+    pub username: Option<String>,
+    /// Private key for SSH remotes, tried after the SSH agent
+    pub ssh_key: Option<PathBuf>,
+    pub ssh_passphrase: Option<String>,
+}
+
+impl CredentialsConfig {
+    /// Username and password of HTTPS clones, when a token is set.
+    pub fn https(&self, default_username: &str) -> Option<(String, String)> {
+        let token = self.token.as_ref()?;
+
+        let credentials = match token.split_once(':') {
+            Some((username, password)) => (username.to_string(), password.to_string()),
+            None => (
+                self.username
+                    .clone()
+                    .unwrap_or_else(|| default_username.to_string()),
+                token.clone(),
+            ),
+        };
+
+        Some(credentials)
+    }
+}
+
+// Keep secrets out of the logs
+impl fmt::Debug for CredentialsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredentialsConfig")
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("username", &self.username)
+            .field("ssh_key", &self.ssh_key)
+            .field(
+                "ssh_passphrase",
+                &self.ssh_passphrase.as_ref().map(|_| "***"),
+            )
+            .finish()
+    }
+}
+
+/// Partial clone filter, so that only the commit graph metadata is fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneFilter {
+    /// `blob:none` - commits and trees, no file content
+    BlobNone,
+    /// `tree:0` - commits only
+    TreeZero,
+}
+
+impl fmt::Display for CloneFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filter = match self {
+            CloneFilter::BlobNone => "blob:none",
+            CloneFilter::TreeZero => "tree:0",
+        };
+        f.write_str(filter)
+    }
+}
+
+impl FromStr for CloneFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blob:none" => Ok(CloneFilter::BlobNone),
+            "tree:0" => Ok(CloneFilter::TreeZero),
+            _ => Err(format!("Unsupported clone filter : {}", s)),
+        }
+    }
+}
+
+/// Where the repository to scan comes from.
+#[derive(Debug, Clone)]
+pub enum RepositorySource {
+    /// Cloned from its url into a temporary location
+    Remote(Url),
+    /// Existing working tree or bare repository, opened in place
+    Local(PathBuf),
 }
 
 #[derive(Debug, Clone)]
 pub struct RepositoryConfig {
-    pub url: Url,
+    pub source: RepositorySource,
     pub all_branches: bool,
+    pub filter: Option<CloneFilter>,
+    pub tls: TlsConfig,
+    pub credentials: CredentialsConfig,
+    /// Folder of the temporary clones
+    pub workdir: PathBuf,
+    /// Folder of the mirrors kept between scans, clones are temporary when not set
+    pub cache: Option<PathBuf>,
 }
 
 impl RepositoryConfig {
-    pub fn new(url: Url, all_branches: bool) -> Self {
-        Self { url, all_branches }
+    pub fn new(
+        url: Url,
+        all_branches: bool,
+        filter: Option<CloneFilter>,
+        tls: TlsConfig,
+        credentials: CredentialsConfig,
+        workdir: PathBuf,
+        cache: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            source: RepositorySource::Remote(url),
+            all_branches,
+            filter,
+            tls,
+            credentials,
+            workdir,
+            cache,
+        }
+    }
+
+    /// Local repositories are always scanned over all their branches.
+    pub fn local(path: PathBuf) -> Self {
+        Self {
+            source: RepositorySource::Local(path),
+            all_branches: true,
+            filter: None,
+            tls: TlsConfig::default(),
+            credentials: CredentialsConfig::default(),
+            workdir: env::temp_dir(),
+            cache: None,
+        }
     }
 }
 
+/// State file of an org or user scan.
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    /// Skip the repositories already in the file instead of starting over
+    pub resume: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserConfig {
     pub url: Url,
     pub all_branches: bool,
+    pub filter: Option<CloneFilter>,
+    /// Used for both the API requests and the clones
+    pub credentials: CredentialsConfig,
+    /// Detected from the url host when not set
+    pub forge: Option<ForgeKind>,
+    /// Derived from the url host when not set
+    pub api_url: Option<Url>,
+    /// Every repository the token can see when not set
+    pub visibility: Option<Visibility>,
+    pub concurrency: ConcurrencyConfig,
+    pub tls: TlsConfig,
+    /// Folder of the temporary clones
+    pub workdir: PathBuf,
+    /// Folder of the mirrors kept between scans, clones are temporary when not set
+    pub cache: Option<PathBuf>,
+    pub checkpoint: Option<CheckpointConfig>,
 }
 
 #[derive(Debug, Clone)]
 pub struct OrgConfig {
     pub url: Url,
     pub all_branches: bool,
+    pub filter: Option<CloneFilter>,
+    /// Used for both the API requests and the clones
+    pub credentials: CredentialsConfig,
+    /// Detected from the url host when not set
+    pub forge: Option<ForgeKind>,
+    /// Derived from the url host when not set
+    pub api_url: Option<Url>,
+    /// Every repository the token can see when not set
+    pub visibility: Option<Visibility>,
+    pub concurrency: ConcurrencyConfig,
+    pub tls: TlsConfig,
+    /// Folder of the temporary clones
+    pub workdir: PathBuf,
+    /// Folder of the mirrors kept between scans, clones are temporary when not set
+    pub cache: Option<PathBuf>,
+    pub checkpoint: Option<CheckpointConfig>,
 }
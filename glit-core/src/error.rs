@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, io};
+
+use crate::types::Stage;
+
+/// Everything that can go wrong while listing, cloning or walking repositories.
+#[derive(Debug)]
+pub enum Error {
+    /// HTTP request to an API or a web page failed
+    Network(reqwest::Error),
+    /// API refused the request until its rate limit is reset
+    RateLimited(String),
+    /// HTTP client could not send the request at all
+    Client(String),
+    /// Repository could not be cloned or opened
+    Clone(git2::Error),
+    /// Git object database could not be read
+    Git(git2::Error),
+    /// Repository without any commit
+    EmptyRepository(String),
+    /// Unexpected url, html or certificate content
+    Parse(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "Network error : {}", e),
+            Error::RateLimited(message) => write!(f, "Rate limited : {}", message),
+            Error::Client(message) => write!(f, "HTTP client error : {}", message),
+            Error::Clone(e) => write!(f, "Clone error : {}", e.message()),
+            Error::Git(e) => write!(f, "Git error : {}", e.message()),
+            Error::EmptyRepository(name) => write!(f, "Empty repository : {}", name),
+            Error::Parse(message) => write!(f, "Parse error : {}", message),
+            Error::Io(e) => write!(f, "IO error : {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            Error::Clone(e) | Error::Git(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        // The API answered, but not with the expected layout
+        if e.is_decode() {
+            return Error::Parse(e.to_string());
+        }
+
+        Error::Network(e)
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Self {
+        Error::Git(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Repository of an org or user scan that failed, kept in the scan result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    pub stage: Stage,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(stage: Stage, error: &Error) -> Self {
+        Self {
+            stage,
+            message: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderMap, LINK},
+    RequestBuilder, Url,
+};
+use serde::de::DeserializeOwned;
+use std::{fmt, str::FromStr};
+use tracing::{error, warn};
+
+use crate::{
+    error::Error,
+    forge::{bitbucket::Bitbucket, gitea::Gitea, github::Github, gitlab::Gitlab},
+    http::HttpClient,
+    parse_selector, parse_url, Factory,
+};
+
+pub mod bitbucket;
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+
+/// Account owning the repositories to enumerate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    User(String),
+    Org(String),
+}
+
+/// Who can see a repository on its forge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private,
+    /// Every member of the instance or the enterprise (GitHub, GitLab, Gitea)
+    Internal,
+    /// Missing or unexpected in the API answer, never kept by a visibility filter
+    Unknown,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let visibility = match self {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+            Visibility::Internal => "internal",
+            Visibility::Unknown => "unknown",
+        };
+        f.write_str(visibility)
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Visibility::Public),
+            "private" => Ok(Visibility::Private),
+            "internal" => Ok(Visibility::Internal),
+            _ => Err(format!("Unsupported visibility : {}", s)),
+        }
+    }
+}
+
+/// Repository found by a listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRepository {
+    pub url: Url,
+    pub visibility: Visibility,
+}
+
+/// Git hosting services with a supported API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgeKind {
+    Github,
+    Gitlab,
+    /// Gitea and its Forgejo fork, Codeberg included
+    Gitea,
+    /// Bitbucket Cloud
+    Bitbucket,
+}
+
+impl ForgeKind {
+    /// Guess the service from the host name, GitHub when nothing matches.
+    pub fn from_url(url: &Url) -> Self {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+
+        if host.contains("gitlab") {
+            ForgeKind::Gitlab
+        } else if host == "bitbucket.org" {
+            ForgeKind::Bitbucket
+        } else if host == "codeberg.org" || host.contains("gitea") || host.contains("forgejo") {
+            ForgeKind::Gitea
+        } else {
+            ForgeKind::Github
+        }
+    }
+
+    /// API client for the instance serving `url`, or for `api_url` when the API
+    /// is not served at its default location.
+    pub fn forge(
+        &self,
+        url: &Url,
+        token: Option<String>,
+        api_url: Option<Url>,
+    ) -> Result<Box<dyn Forge>, Error> {
+        let forge: Box<dyn Forge> = match (self, api_url.map(api_base)) {
+            (ForgeKind::Github, Some(api_url)) => Box::new(Github::new(api_url, token)),
+            (ForgeKind::Github, None) => Box::new(Github::for_instance(url, token)?),
+            (ForgeKind::Gitlab, Some(api_url)) => Box::new(Gitlab::new(api_url, token)),
+            (ForgeKind::Gitlab, None) => Box::new(Gitlab::for_instance(url, token)?),
+            (ForgeKind::Gitea, Some(api_url)) => Box::new(Gitea::new(api_url, token)),
+            (ForgeKind::Gitea, None) => Box::new(Gitea::for_instance(url, token)?),
+            (ForgeKind::Bitbucket, Some(api_url)) => Box::new(Bitbucket::new(api_url, token)),
+            (ForgeKind::Bitbucket, None) => Box::new(Bitbucket::public(token)),
+        };
+
+        Ok(forge)
+    }
+
+    /// Username the service expects along a token used as HTTPS password.
+    pub fn token_username(&self) -> &'static str {
+        match self {
+            ForgeKind::Github => "x-access-token",
+            ForgeKind::Gitlab | ForgeKind::Gitea => "oauth2",
+            ForgeKind::Bitbucket => "x-token-auth",
+        }
+    }
+}
+
+impl fmt::Display for ForgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let forge = match self {
+            ForgeKind::Github => "github",
+            ForgeKind::Gitlab => "gitlab",
+            ForgeKind::Gitea => "gitea",
+            ForgeKind::Bitbucket => "bitbucket",
+        };
+        f.write_str(forge)
+    }
+}
+
+impl FromStr for ForgeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(ForgeKind::Github),
+            "gitlab" => Ok(ForgeKind::Gitlab),
+            "gitea" | "forgejo" | "codeberg" => Ok(ForgeKind::Gitea),
+            "bitbucket" => Ok(ForgeKind::Bitbucket),
+            _ => Err(format!("Unsupported forge : {}", s)),
+        }
+    }
+}
+
+/// Repository enumeration through the API of a git hosting service.
+#[async_trait]
+pub trait Forge: Send + Sync {
+    /// Clonable url and visibility of every repository of `owner` the token can see.
+    async fn repositories(
+        &self,
+        client: &HttpClient,
+        owner: &Owner,
+    ) -> Result<Vec<RemoteRepository>, Error>;
+}
+
+/// User or organization whose repositories are scanned, and how to list them.
+#[derive(Debug, Clone)]
+pub(crate) struct Account {
+    pub url: Url,
+    pub owner: Owner,
+    pub forge: ForgeKind,
+    api_url: Option<Url>,
+    visibility: Option<Visibility>,
+    page_url: Url,
+}
+
+impl Account {
+    /// Account at `url`, `kind` being `Owner::User` or `Owner::Org`.
+    pub fn new(
+        kind: fn(String) -> Owner,
+        url: Url,
+        forge: Option<ForgeKind>,
+        api_url: Option<Url>,
+        visibility: Option<Visibility>,
+    ) -> Result<Self, Error> {
+        let forge = forge.unwrap_or_else(|| ForgeKind::from_url(&url));
+
+        // Nested GitLab groups keep their full path
+        let name = url
+            .path_segments()
+            .ok_or_else(|| Error::Parse(format!("No account path in {}", url)))?
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        let owner = kind(name);
+
+        // GitHub web interface layout, only scraped when the GitHub API fails
+        let page_url = match &owner {
+            Owner::Org(name) => {
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| Error::Parse(format!("No host in {}", url)))?;
+                format!(
+                    "{}://{}/orgs/{}/repositories?q=&type=source",
+                    url.scheme(),
+                    host,
+                    name
+                )
+            }
+            Owner::User(_) => format!("{}?tab=repositories&type=source", url),
+        };
+        let page_url = parse_url(&page_url)?;
+
+        Ok(Self {
+            url,
+            owner,
+            forge,
+            api_url,
+            visibility,
+            page_url,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.owner {
+            Owner::User(name) | Owner::Org(name) => name,
+        }
+    }
+
+    /// Urls of the repositories with the requested visibility, through the API
+    /// of the forge. The GitHub web interface is scraped by `F` when the API fails.
+    pub async fn repositories<F: Factory + Send>(
+        &self,
+        client: &HttpClient,
+        token: Option<String>,
+    ) -> Result<Vec<Url>, Error> {
+        let forge = self.forge.forge(&self.url, token, self.api_url.clone())?;
+
+        match forge.repositories(client, &self.owner).await {
+            Ok(repositories) => Ok(repositories
+                .into_iter()
+                .filter(|repository| {
+                    let Some(visibility) = self.visibility else {
+                        return true;
+                    };
+                    if repository.visibility == Visibility::Unknown {
+                        warn!("Skip {} : unknown visibility", repository.url);
+                    }
+                    visibility == repository.visibility
+                })
+                .map(|repository| repository.url)
+                .collect()),
+            Err(e) if self.forge != ForgeKind::Github => Err(e),
+            // The web interface only shows public repositories
+            Err(e) if self.visibility.is_some_and(|v| v != Visibility::Public) => Err(e),
+            Err(e) => {
+                error!("API listing failed, fallback to scraping : {}", e);
+                let selector = match self.owner {
+                    Owner::Org(_) => parse_selector(
+                        r#"main > div > div > div > div > div > div > ul > li > div > div > div > h3 > a"#,
+                    )?,
+                    Owner::User(_) => {
+                        parse_selector(r#"turbo-frame > div > div > ul > li > div > div > h3 > a"#)?
+                    }
+                };
+                F::_scrape_repositories(client, self.url.clone(), self.page_url.clone(), selector)
+                    .await
+            }
+        }
+    }
+}
+
+/// `{scheme}://{host}[:{port}]/{path}` of the instance serving `url`.
+pub(crate) fn instance_url(url: &Url, path: &str) -> Result<Url, Error> {
+    let host = url.host_str().unwrap_or_default();
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    parse_url(&format!("{}://{}/{}", url.scheme(), authority, path))
+}
+
+/// `api_url` ending with a `/`, otherwise joining an endpoint would replace its
+/// last segment: `https://ghe.corp/api/v3` would become `https://ghe.corp/api/orgs/..`.
+pub(crate) fn api_base(mut api_url: Url) -> Url {
+    if !api_url.path().ends_with('/') {
+        let path = format!("{}/", api_url.path());
+        api_url.set_path(&path);
+    }
+
+    api_url
+}
+
+/// `endpoint` of the API served at `api_url`.
+pub(crate) fn api_endpoint(api_url: &Url, endpoint: &str) -> Result<Url, Error> {
+    api_url
+        .join(endpoint)
+        .map_err(|e| Error::Parse(format!("{}{} : {}", api_url, endpoint, e)))
+}
+
+/// Follow the `rel="next"` links of a paginated JSON array endpoint.
+pub(crate) async fn get_paginated<T, F>(
+    client: &HttpClient,
+    first_page: Url,
+    authorize: F,
+) -> Result<Vec<T>, Error>
+where
+    T: DeserializeOwned,
+    F: Fn(RequestBuilder) -> RequestBuilder,
+{
+    let mut items = Vec::new();
+    let mut next = Some(first_page);
+
+    while let Some(page) = next {
+        let resp = client.send(authorize(client.get(page))).await?;
+        next = next_page_link(resp.headers());
+        items.extend(resp.json::<Vec<T>>().await?);
+    }
+
+    Ok(items)
+}
+
+/// Parse `Link: <https://...&page=2>; rel="next", <https://...&page=5>; rel="last"`.
+pub(crate) fn next_page_link(headers: &HeaderMap) -> Option<Url> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+
+    link.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim().replace(' ', "") == r#"rel="next""#);
+
+        if is_next {
+            let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+            Url::parse(target).ok()
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, Url};
+use serde::{de::IgnoredAny, Deserialize};
+use tracing::info;
+
+use crate::{
+    error::Error,
+    forge::{api_endpoint, Forge, Owner, RemoteRepository, Visibility},
+    http::HttpClient,
+};
+
+const BITBUCKET_API_URL: &str = "https://api.bitbucket.org/2.0/";
+const REPO_PER_PAGE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct BitbucketPage {
+    values: Vec<BitbucketRepository>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketRepository {
+    full_name: String,
+    links: BitbucketLinks,
+    /// Only present on forks
+    parent: Option<IgnoredAny>,
+    #[serde(default)]
+    is_private: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketLinks {
+    clone: Vec<BitbucketCloneLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketCloneLink {
+    name: String,
+    href: String,
+}
+
+/// Bitbucket Cloud 2.0 API, `/repositories/{workspace}`. Repositories of a user
+/// live in their personal workspace, so users and workspaces are listed alike.
+#[derive(Debug, Clone)]
+pub struct Bitbucket {
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl Bitbucket {
+    pub fn new(api_url: Url, token: Option<String>) -> Self {
+        Self { api_url, token }
+    }
+
+    /// API of bitbucket.org
+    pub fn public(token: Option<String>) -> Self {
+        Self::new(Url::parse(BITBUCKET_API_URL).unwrap(), token)
+    }
+
+    /// `username:app_password` is sent as basic auth, anything else as a bearer token.
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => match token.split_once(':') {
+                Some((username, app_password)) => request.basic_auth(username, Some(app_password)),
+                None => request.bearer_auth(token),
+            },
+            None => request,
+        }
+    }
+
+    fn first_page(&self, owner: &Owner) -> Result<Url, Error> {
+        let workspace = match owner {
+            Owner::User(name) | Owner::Org(name) => name,
+        };
+
+        let mut url = api_endpoint(&self.api_url, &format!("repositories/{}", workspace))?;
+        url.query_pairs_mut()
+            .append_pair("pagelen", &REPO_PER_PAGE.to_string());
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Forge for Bitbucket {
+    async fn repositories(
+        &self,
+        client: &HttpClient,
+        owner: &Owner,
+    ) -> Result<Vec<RemoteRepository>, Error> {
+        let first_page = self.first_page(owner)?;
+        info!("List repositories from {}", first_page);
+
+        // Pagination is given by the `next` field of the body, not by a Link header
+        let mut repositories = Vec::new();
+        let mut next = Some(first_page);
+        while let Some(page) = next {
+            let resp = client.send(self.authorize(client.get(page))).await?;
+            let page: BitbucketPage = resp.json().await?;
+
+            next = page.next.and_then(|next| Url::parse(&next).ok());
+            repositories.extend(page.values);
+        }
+
+        let urls = repositories
+            .into_iter()
+            .filter(|repository| repository.parent.is_none())
+            .filter_map(|repository| {
+                let url = repository
+                    .links
+                    .clone
+                    .iter()
+                    .find(|link| link.name == "https")
+                    .and_then(|link| Url::parse(&link.href).ok())
+                    .map(|mut url| {
+                        // Authenticated listings embed the username in the clone url
+                        let _ = url.set_username("");
+                        url
+                    });
+
+                if url.is_none() {
+                    info!("Skip {} : no https clone url", repository.full_name);
+                }
+                let visibility = match repository.is_private {
+                    true => Visibility::Private,
+                    false => Visibility::Public,
+                };
+                url.map(|url| RemoteRepository { url, visibility })
+            })
+            .collect();
+
+        Ok(urls)
+    }
+}
@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use reqwest::{header::AUTHORIZATION, RequestBuilder, Url};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    error::Error,
+    forge::{
+        api_endpoint, get_paginated, instance_url, Forge, Owner, RemoteRepository, Visibility,
+    },
+    http::HttpClient,
+};
+
+const REPO_PER_PAGE: usize = 50;
+
+#[derive(Debug, Deserialize)]
+struct GiteaRepository {
+    full_name: String,
+    clone_url: String,
+    fork: bool,
+    #[serde(default)]
+    private: bool,
+    #[serde(default)]
+    internal: bool,
+}
+
+/// Gitea compatible API (Gitea, Forgejo, Codeberg), `/api/v1/users/{user}/repos`
+/// and `/api/v1/orgs/{org}/repos`.
+#[derive(Debug, Clone)]
+pub struct Gitea {
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl Gitea {
+    pub fn new(api_url: Url, token: Option<String>) -> Self {
+        Self { api_url, token }
+    }
+
+    /// API of the instance serving `url`.
+    pub fn for_instance(url: &Url, token: Option<String>) -> Result<Self, Error> {
+        Ok(Self::new(instance_url(url, "api/v1/")?, token))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.header(AUTHORIZATION, format!("token {}", token)),
+            None => request,
+        }
+    }
+
+    fn first_page(&self, owner: &Owner) -> Result<Url, Error> {
+        let endpoint = match owner {
+            Owner::User(name) => format!("users/{}/repos", name),
+            Owner::Org(name) => format!("orgs/{}/repos", name),
+        };
+
+        let mut url = api_endpoint(&self.api_url, &endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("limit", &REPO_PER_PAGE.to_string());
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Forge for Gitea {
+    async fn repositories(
+        &self,
+        client: &HttpClient,
+        owner: &Owner,
+    ) -> Result<Vec<RemoteRepository>, Error> {
+        let first_page = self.first_page(owner)?;
+        info!("List repositories from {}", first_page);
+
+        let repositories: Vec<GiteaRepository> =
+            get_paginated(client, first_page, |request| self.authorize(request)).await?;
+
+        let urls = repositories
+            .into_iter()
+            .filter(|repository| !repository.fork)
+            .filter_map(|repository| {
+                let url = Url::parse(&repository.clone_url).ok();
+                if url.is_none() {
+                    info!("Skip {} : invalid clone url", repository.full_name);
+                }
+                let visibility = match (repository.private, repository.internal) {
+                    (true, _) => Visibility::Private,
+                    (false, true) => Visibility::Internal,
+                    (false, false) => Visibility::Public,
+                };
+                url.map(|url| RemoteRepository { url, visibility })
+            })
+            .collect();
+
+        Ok(urls)
+    }
+}
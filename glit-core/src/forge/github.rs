@@ -0,0 +1,146 @@
+use async_trait::async_trait;
+use reqwest::{
+    header::{ACCEPT, USER_AGENT},
+    RequestBuilder, Url,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    error::Error,
+    forge::{
+        api_endpoint, get_paginated, instance_url, Forge, Owner, RemoteRepository, Visibility,
+    },
+    http::HttpClient,
+};
+
+const GITHUB_API_URL: &str = "https://api.github.com/";
+const REPO_PER_PAGE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct GithubRepository {
+    name: String,
+    clone_url: String,
+    fork: bool,
+    #[serde(default)]
+    private: bool,
+    /// `public`, `private` or `internal`, missing on older Enterprise Servers
+    visibility: Option<String>,
+}
+
+impl GithubRepository {
+    fn visibility(&self) -> Visibility {
+        match self.visibility.as_deref().map(str::parse) {
+            Some(Ok(visibility)) => visibility,
+            _ if self.private => Visibility::Private,
+            _ => Visibility::Public,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    login: String,
+}
+
+/// GitHub REST API, `/users/{user}/repos` and `/orgs/{org}/repos`, of github.com
+/// or of a GitHub Enterprise Server.
+#[derive(Debug, Clone)]
+pub struct Github {
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl Github {
+    pub fn new(api_url: Url, token: Option<String>) -> Self {
+        Self { api_url, token }
+    }
+
+    /// Public API of github.com
+    pub fn public(token: Option<String>) -> Self {
+        Self::new(Url::parse(GITHUB_API_URL).unwrap(), token)
+    }
+
+    /// github.com, or the `/api/v3` endpoint of the Enterprise Server serving `url`.
+    pub fn for_instance(url: &Url, token: Option<String>) -> Result<Self, Error> {
+        match url.host_str() {
+            Some("github.com") | Some("www.github.com") => Ok(Self::public(token)),
+            _ => Ok(Self::new(instance_url(url, "api/v3/")?, token)),
+        }
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = request
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "glit");
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Login of the token owner, if any.
+    async fn authenticated_login(&self, client: &HttpClient) -> Option<String> {
+        self.token.as_ref()?;
+
+        let url = self.api_url.join("user").ok()?;
+        let resp = client.send(self.authorize(client.get(url))).await.ok()?;
+        resp.json::<GithubUser>().await.ok().map(|user| user.login)
+    }
+
+    fn first_page(&self, owner: &Owner, own_account: bool) -> Result<Url, Error> {
+        // Forks are left out, as the `type=source` filter of the web interface does.
+        // Private repositories of a user are only listed to the user itself.
+        let endpoint = match owner {
+            Owner::User(_) if own_account => "user/repos?affiliation=owner".to_string(),
+            Owner::User(name) => format!("users/{}/repos?type=owner", name),
+            Owner::Org(name) => format!("orgs/{}/repos?type=sources", name),
+        };
+
+        let mut url = api_endpoint(&self.api_url, &endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("per_page", &REPO_PER_PAGE.to_string());
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Forge for Github {
+    async fn repositories(
+        &self,
+        client: &HttpClient,
+        owner: &Owner,
+    ) -> Result<Vec<RemoteRepository>, Error> {
+        let own_account = match owner {
+            Owner::User(name) => self
+                .authenticated_login(client)
+                .await
+                .is_some_and(|login| login.eq_ignore_ascii_case(name)),
+            Owner::Org(_) => false,
+        };
+
+        let first_page = self.first_page(owner, own_account)?;
+        info!("List repositories from {}", first_page);
+
+        let repositories: Vec<GithubRepository> =
+            get_paginated(client, first_page, |request| self.authorize(request)).await?;
+
+        let urls = repositories
+            .into_iter()
+            .filter(|repository| !repository.fork)
+            .filter_map(|repository| {
+                let url = Url::parse(&repository.clone_url).ok();
+                if url.is_none() {
+                    info!("Skip {} : invalid clone url", repository.name);
+                }
+                url.map(|url| RemoteRepository {
+                    url,
+                    visibility: repository.visibility(),
+                })
+            })
+            .collect();
+
+        Ok(urls)
+    }
+}
@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, Url};
+use serde::{de::IgnoredAny, Deserialize};
+use tracing::info;
+
+use crate::{
+    error::Error,
+    forge::{
+        api_endpoint, get_paginated, instance_url, Forge, Owner, RemoteRepository, Visibility,
+    },
+    http::HttpClient,
+};
+
+const REPO_PER_PAGE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct GitlabProject {
+    path_with_namespace: String,
+    http_url_to_repo: String,
+    /// Only present on forks
+    forked_from_project: Option<IgnoredAny>,
+    /// `public`, `internal` or `private`
+    visibility: Option<String>,
+}
+
+/// GitLab v4 API, `/users/{user}/projects` and `/groups/{group}/projects`
+/// including the projects of nested subgroups.
+#[derive(Debug, Clone)]
+pub struct Gitlab {
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl Gitlab {
+    pub fn new(api_url: Url, token: Option<String>) -> Self {
+        Self { api_url, token }
+    }
+
+    /// API of the instance serving `url`, gitlab.com or self-hosted.
+    pub fn for_instance(url: &Url, token: Option<String>) -> Result<Self, Error> {
+        Ok(Self::new(instance_url(url, "api/v4/")?, token))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.header("PRIVATE-TOKEN", token),
+            None => request,
+        }
+    }
+
+    fn first_page(&self, owner: &Owner) -> Result<Url, Error> {
+        // Groups are addressed by their url-encoded full path: `group%2Fsubgroup`
+        let endpoint = match owner {
+            Owner::User(name) => format!("users/{}/projects?", encode(name)),
+            Owner::Org(name) => format!("groups/{}/projects?include_subgroups=true&", encode(name)),
+        };
+
+        api_endpoint(
+            &self.api_url,
+            &format!("{}per_page={}", endpoint, REPO_PER_PAGE),
+        )
+    }
+}
+
+fn encode(path: &str) -> String {
+    path.trim_matches('/').replace('/', "%2F")
+}
+
+#[async_trait]
+impl Forge for Gitlab {
+    async fn repositories(
+        &self,
+        client: &HttpClient,
+        owner: &Owner,
+    ) -> Result<Vec<RemoteRepository>, Error> {
+        let first_page = self.first_page(owner)?;
+        info!("List projects from {}", first_page);
+
+        let projects: Vec<GitlabProject> =
+            get_paginated(client, first_page, |request| self.authorize(request)).await?;
+
+        let urls = projects
+            .into_iter()
+            .filter(|project| project.forked_from_project.is_none())
+            .filter_map(|project| {
+                let url = Url::parse(&project.http_url_to_repo).ok();
+                if url.is_none() {
+                    info!("Skip {} : invalid clone url", project.path_with_namespace);
+                }
+                let visibility = project
+                    .visibility
+                    .as_deref()
+                    .and_then(|visibility| visibility.parse().ok())
+                    .unwrap_or(Visibility::Unknown);
+                url.map(|url| RemoteRepository { url, visibility })
+            })
+            .collect();
+
+        Ok(urls)
+    }
+}
@@ -0,0 +1,210 @@
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, RequestBuilder, Response, StatusCode, Url,
+};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::Semaphore,
+    time::{sleep, sleep_until, Instant},
+};
+use tracing::warn;
+
+use crate::{config::HttpConfig, error::Error};
+
+/// HTTP client shared by the API listings and the scraping fallback.
+///
+/// Requests wait for a free slot, and a rate limit met by one request pauses
+/// all of them until the limit is reset. Rate limited and failed requests
+/// are retried with an exponential backoff.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    config: HttpConfig,
+    permits: Arc<Semaphore>,
+    /// End of the current rate limit window, shared by every request
+    paused_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl HttpClient {
+    pub fn new(client: Client) -> Self {
+        Self::with_config(client, HttpConfig::default())
+    }
+
+    pub fn with_config(client: Client, config: HttpConfig) -> Self {
+        Self {
+            client,
+            permits: Arc::new(Semaphore::new(config.max_concurrent_requests.max(1))),
+            config,
+            paused_until: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn get(&self, url: Url) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send `request`, retrying until it succeeds, fails for good or runs out of retries.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| Error::Client("HTTP client closed".to_string()))?;
+
+        let mut attempt = 0;
+        loop {
+            self.wait_for_limit_reset().await;
+
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| Error::Client("Request body can not be retried".to_string()))?;
+
+            match attempt_request.send().await {
+                Ok(resp) => match self.retry_delay(&resp, attempt) {
+                    Some(wait) if attempt < self.config.max_retries => {
+                        warn!(
+                            "{} answered {}, waiting {:?} before retry {}/{}",
+                            resp.url(),
+                            resp.status(),
+                            wait,
+                            attempt + 1,
+                            self.config.max_retries
+                        );
+                        // A rate limit applies to every request, an error only to this one
+                        if is_rate_limited(&resp) {
+                            self.pause(wait);
+                        } else {
+                            sleep(wait).await;
+                        }
+                    }
+                    _ => {
+                        self.pause_when_exhausted(resp.headers());
+                        return check_status(resp);
+                    }
+                },
+                Err(e)
+                    if (e.is_timeout() || e.is_connect()) && attempt < self.config.max_retries =>
+                {
+                    let wait = self.backoff(attempt);
+                    warn!(
+                        "{}, waiting {:?} before retry {}/{}",
+                        e,
+                        wait,
+                        attempt + 1,
+                        self.config.max_retries
+                    );
+                    sleep(wait).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            attempt += 1;
+        }
+    }
+
+    /// Delay before retrying a rate limited or failed request, `None` when it must not be retried.
+    fn retry_delay(&self, resp: &Response, attempt: u32) -> Option<Duration> {
+        let headers = resp.headers();
+
+        if is_rate_limited(resp) {
+            let wait = retry_after(headers)
+                .or_else(|| rate_limit_reset(headers))
+                .unwrap_or_else(|| self.backoff(attempt));
+            // Waiting for a primary rate limit may take up to an hour
+            return (wait <= self.config.max_wait).then_some(wait);
+        }
+
+        if resp.status().is_server_error() {
+            return Some(retry_after(headers).unwrap_or_else(|| self.backoff(attempt)));
+        }
+
+        None
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.config.base_delay * 2u32.saturating_pow(attempt)
+    }
+
+    /// Successful answers announce an exhausted limit before the next request is refused.
+    fn pause_when_exhausted(&self, headers: &HeaderMap) {
+        if !is_exhausted(headers) {
+            return;
+        }
+
+        if let Some(wait) = rate_limit_reset(headers).filter(|wait| *wait <= self.config.max_wait) {
+            warn!("Rate limit exhausted, next requests wait {:?}", wait);
+            self.pause(wait);
+        }
+    }
+
+    fn pause(&self, wait: Duration) {
+        let until = Instant::now() + wait;
+        let mut paused_until = self
+            .paused_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if paused_until.is_none_or(|paused_until| paused_until < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    async fn wait_for_limit_reset(&self) {
+        let paused_until = *self
+            .paused_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(until) = paused_until {
+            sleep_until(until).await;
+        }
+    }
+}
+
+/// Turn an error status into an error, telling rate limits apart.
+fn check_status(resp: Response) -> Result<Response, Error> {
+    if is_rate_limited(&resp) {
+        return Err(Error::RateLimited(format!(
+            "{} answered {}",
+            resp.url(),
+            resp.status()
+        )));
+    }
+
+    Ok(resp.error_for_status()?)
+}
+
+/// `429`, or `403` with an exhausted limit (GitHub primary rate limit) or a
+/// `Retry-After` header (GitHub secondary rate limit).
+fn is_rate_limited(resp: &Response) -> bool {
+    let headers = resp.headers();
+
+    match resp.status() {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => is_exhausted(headers) || headers.contains_key(RETRY_AFTER),
+        _ => false,
+    }
+}
+
+fn header<T: FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn is_exhausted(headers: &HeaderMap) -> bool {
+    header::<u64>(headers, "x-ratelimit-remaining") == Some(0)
+}
+
+/// `Retry-After` in seconds, the HTTP date form is not used by the forges.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    header::<u64>(headers, RETRY_AFTER.as_str()).map(Duration::from_secs)
+}
+
+/// Time left until `X-RateLimit-Reset`, an epoch timestamp in seconds.
+fn rate_limit_reset(headers: &HeaderMap) -> Option<Duration> {
+    let reset = header::<u64>(headers, "x-ratelimit-reset")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
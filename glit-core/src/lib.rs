@@ -1,35 +1,45 @@
-use crate::{config::RepositoryConfig, repo::RepositoryFactory};
+use crate::{
+    checkpoint::Checkpoint,
+    config::{CloneFilter, ConcurrencyConfig, CredentialsConfig, TlsConfig},
+    http::HttpClient,
+    scan::{Inspect, Scan, ScanStream},
+};
 use ahash::RandomState;
 use async_trait::async_trait;
-use crossbeam_channel::bounded;
 use dashmap::DashMap;
-use futures_util::future::join_all;
-use rayon::ThreadPoolBuilder;
+pub use error::{Error, Failure};
+use futures_util::{future::join_all, StreamExt};
 use repo::Repository;
-use reqwest::{Client, Url};
+use reqwest::Url;
 use scraper::{Html, Selector};
-use std::{
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
-    time::Instant,
-};
-use tracing::{error, info};
+use std::{path::PathBuf, sync::Arc};
+use tracing::info;
 use types::RepoName;
 
+pub mod cache;
+pub mod checkpoint;
 pub mod config;
+pub mod error;
+pub mod forge;
+pub mod http;
 pub mod log;
 pub mod org;
 pub mod repo;
+pub mod scan;
 pub mod types;
 pub mod user;
 
 const NUMBER_OF_REPO_PER_PAGE: usize = 30;
 
+/// Repositories extracted by a scan, and the failure met on every other one.
+pub type ScanResult = (
+    DashMap<RepoName, Repository, RandomState>,
+    DashMap<RepoName, Failure, RandomState>,
+);
+
 #[async_trait]
 pub trait Factory {
-    async fn _repositories_count(client: &Client, url: Url) -> usize;
+    async fn _repositories_count(client: &HttpClient, url: Url) -> Result<usize, Error>;
 
     fn _pages_count(repo_count: usize) -> usize {
         let modulo = repo_count % NUMBER_OF_REPO_PER_PAGE;
@@ -40,145 +50,165 @@ pub trait Factory {
         }
     }
 
-    fn _build_repo_links(page_url: Url, repo_count: usize, pages_count: usize) -> Vec<Url> {
+    fn _build_repo_links(
+        page_url: Url,
+        repo_count: usize,
+        pages_count: usize,
+    ) -> Result<Vec<Url>, Error> {
         let mut pages_urls = Vec::with_capacity(repo_count);
         for i in 1..pages_count + 1 {
             let url = format!("{}&page={}", page_url, i);
-            pages_urls.push(Url::parse(&url).unwrap());
+            pages_urls.push(parse_url(&url)?);
         }
 
-        pages_urls
+        Ok(pages_urls)
     }
-}
 
-#[async_trait]
-pub trait ExtractLog {
-    async fn common_log_feature(
-        &self,
-        client: &Client,
+    /// Fallback enumeration, scraping the repositories pages of the web interface.
+    async fn _scrape_repositories(
+        client: &HttpClient,
+        url: Url,
+        page_url: Url,
         selector: Selector,
-    ) -> DashMap<RepoName, Repository, ahash::RandomState> {
-        let start_a = Instant::now();
-
-        let repo_count = self.get_repo_count();
-        let all_branches = self.get_all_branches();
-        let pages_urls = self.get_pages_url();
-        let url = self.get_url();
-
-        let (tx_url, rx_url) = bounded(repo_count);
-        let mut tokio_handles = Vec::with_capacity(pages_urls.len());
-        for page in pages_urls {
-            let client = client.clone();
-            let url = url.clone();
-            let tx_url = tx_url.clone();
-            let selector = selector.clone();
-
-            let handle = tokio::spawn(async move {
-                let resp = client.get(page).send().await.unwrap();
-                let text = resp.text().await.unwrap();
+    ) -> Result<Vec<Url>, Error> {
+        let repo_count = Self::_repositories_count(client, page_url.clone()).await?;
+        let pages_count = Self::_pages_count(repo_count);
+        let pages_urls = Self::_build_repo_links(page_url, repo_count, pages_count)?;
+
+        let pages = pages_urls.into_iter().map(|page| {
+            let url = &url;
+            let selector = &selector;
+
+            async move {
+                let text = client.send(client.get(page)).await?.text().await?;
                 let parser = Html::parse_document(&text);
 
                 parser
-                    .select(&selector)
-                    .map(|link| {
-                        let endpoint_url = link.value().attr("href").unwrap().to_string();
-                        let repo_name = endpoint_url.split('/').last().unwrap();
+                    .select(selector)
+                    .filter_map(|link| link.value().attr("href"))
+                    .filter_map(|endpoint_url| endpoint_url.split('/').next_back())
+                    .map(|repo_name| {
                         let repo_url = format!("{}{}/", url, repo_name);
-                        let sending = tx_url.send(Url::parse(&repo_url).unwrap());
-                        match sending {
-                            Ok(_) => info!("Send url {}", repo_url),
-                            Err(e) => {
-                                error!("Failed to send {} with : [{:?}]", repo_url, e)
-                            }
-                        }
+                        info!("Found url {}", repo_url);
+                        parse_url(&repo_url)
                     })
-                    .for_each(drop);
-            });
+                    .collect::<Result<Vec<Url>, Error>>()
+            }
+        });
 
-            tokio_handles.push(handle);
+        let mut repositories = Vec::new();
+        for urls in join_all(pages).await {
+            repositories.extend(urls?);
         }
-        drop(tx_url);
-
-        let mut queue_handles = Vec::with_capacity(repo_count);
-        let (tx, rx) = bounded(repo_count);
-
-        for _ in 0..repo_count {
-            let tx = tx.clone();
-            let rx_url = rx_url.clone();
 
-            let handle = rayon::spawn(move || {
-                let clonable_url = rx_url.recv().unwrap();
-                let repo_config = RepositoryConfig::new(clonable_url, all_branches);
-                let repo = RepositoryFactory::with_config(repo_config).create();
-                tx.send(repo).unwrap();
-                drop(tx);
-            });
+        Ok(repositories)
+    }
+}
 
-            queue_handles.push(handle)
+#[async_trait]
+pub trait ExtractLog {
+    /// Clone and walk every repository in the background, yielding each one
+    /// as soon as it is walked. A failing repository is reported with its
+    /// error and does not stop the others. Each walked repository is saved to
+    /// the checkpoint, when set.
+    fn log_stream(&self) -> ScanStream {
+        Scan {
+            repositories: self.get_repositories(),
+            all_branches: self.get_all_branches(),
+            filter: self.get_filter(),
+            tls: self.get_tls(),
+            credentials: self.get_credentials(),
+            concurrency: self.get_concurrency(),
+            workdir: self.get_workdir(),
+            cache: self.get_cache(),
+            checkpoint: self.get_checkpoint(),
         }
-        drop(tx);
-        drop(rx_url);
-
-        let current_num_thread = rayon::current_num_threads();
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(current_num_thread)
-            .build()
-            .unwrap();
-
-        let dash: Arc<DashMap<RepoName, Repository, RandomState>> = Arc::new(
-            DashMap::with_capacity_and_hasher(repo_count, RandomState::new()),
-        );
-
-        let atomic_count = Arc::new(AtomicUsize::new(0));
-
-        let dash_result = pool.scope(move |scope| {
-            for _ in 0..repo_count {
-                let dash = dash.clone();
-                let rx = rx.clone();
-                let atomic_count = atomic_count.clone();
-
-                scope.spawn(move |_| {
-                    let repo = rx.recv().unwrap();
-                    drop(rx);
-
-                    let data = repo.clone().extract_log();
-                    let repo_name_key = RepoName(repo.name);
-                    dash.insert(repo_name_key, data);
-                    atomic_count.fetch_add(1, Ordering::Relaxed);
-                    info!(
-                        "Repository handled : {}/{}",
-                        atomic_count.load(Ordering::Relaxed),
-                        repo_count
-                    );
-                })
-            }
-            dash
-        });
-        drop(pool);
+        .spawn()
+    }
 
-        join_all(tokio_handles).await;
+    /// Every repository of `log_stream`, once the scan is over. Each item is
+    /// handed to `inspect` as soon as it is walked.
+    async fn common_log_feature(&self, inspect: &mut Inspect<'_>) -> ScanResult {
+        let dash: DashMap<RepoName, Repository, RandomState> =
+            DashMap::with_capacity_and_hasher(self.get_repo_count(), RandomState::new());
+        let failures: DashMap<RepoName, Failure, RandomState> =
+            DashMap::with_hasher(RandomState::new());
+
+        let mut stream = self.log_stream();
+        while let Some(item) = stream.next().await {
+            inspect(&item);
+
+            let (name, repo) = item;
+            match repo {
+                Ok(repo) => {
+                    dash.insert(name, repo);
+                }
+                Err(failure) => {
+                    failures.insert(name, failure);
+                }
+            }
+        }
 
-        info!(
-            "Fetching and Cloning handled in {:?} for {}",
-            start_a.elapsed(),
-            repo_count
-        );
+        (dash, failures)
+    }
 
-        Arc::try_unwrap(dash_result).unwrap()
+    async fn extract_log(self, client: &HttpClient) -> Self
+    where
+        Self: Sized + Send,
+    {
+        self.extract_log_with(client, &mut |_| {}).await
     }
 
-    async fn extract_log(mut self, client: &Client) -> Self;
+    /// `extract_log`, handing each repository to `inspect` as soon as it is walked.
+    async fn extract_log_with(mut self, client: &HttpClient, inspect: &mut Inspect<'_>) -> Self;
 
     // Common Getter
     fn get_repo_count(&self) -> usize;
     fn get_all_branches(&self) -> bool;
+    fn get_filter(&self) -> Option<CloneFilter>;
+    fn get_tls(&self) -> TlsConfig;
+    fn get_credentials(&self) -> CredentialsConfig;
+    fn get_concurrency(&self) -> ConcurrencyConfig;
+    fn get_workdir(&self) -> PathBuf;
+    fn get_cache(&self) -> Option<PathBuf>;
+    fn get_checkpoint(&self) -> Option<Arc<Checkpoint>>;
     fn get_url(&self) -> Url;
-    fn get_pages_url(&self) -> Vec<Url>;
+    fn get_repositories(&self) -> Vec<Url>;
+}
+
+/// Path of a repository url, `namespace/name` without `.git`, so that
+/// repositories of the same name in different groups or owners stay apart.
+pub(crate) fn repo_name(url: &Url) -> RepoName {
+    let path = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    RepoName(path.trim_end_matches(".git").to_string())
+}
+
+pub(crate) fn parse_url(url: &str) -> Result<Url, Error> {
+    Url::parse(url).map_err(|e| Error::Parse(format!("{} : {}", url, e)))
+}
+
+pub(crate) fn parse_selector(selector: &str) -> Result<Selector, Error> {
+    Selector::parse(selector).map_err(|e| Error::Parse(format!("{} : {:?}", selector, e)))
 }
 
 pub struct Logger;
 impl Logger {
-    pub async fn log_for<T: ExtractLog>(t: T, client: &Client) -> T {
+    pub async fn log_for<T: ExtractLog + Send>(t: T, client: &HttpClient) -> T {
         t.extract_log(client).await
     }
+
+    pub async fn log_for_with<T: ExtractLog + Send>(
+        t: T,
+        client: &HttpClient,
+        inspect: &mut Inspect<'_>,
+    ) -> T {
+        t.extract_log_with(client, inspect).await
+    }
 }
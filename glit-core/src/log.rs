@@ -1,19 +1,31 @@
+use ahash::{HashMap, HashMapExt};
 use git2::{Oid, Sort};
 use std::{path::PathBuf, thread};
 use tracing::info;
 
-use crate::repo::Committers;
+use crate::{
+    error::Error,
+    repo::{Committers, Taggers},
+    types::{BranchName, TagName},
+};
 
 pub struct Log {}
 
 impl Log {
-    pub fn build(path: PathBuf) -> Committers {
-        let repo = git2::Repository::open_bare(path.as_path()).unwrap();
-        let mut revwalk = repo.revwalk().unwrap();
-        revwalk.set_sorting(Sort::TIME).unwrap();
-
-        let mut repo_data = Committers::new();
-        revwalk.push_head().unwrap();
+    /// Walk every branch reference of the repository at `path`.
+    ///
+    /// Branches are walked in order and the tips of the ones already walked are
+    /// hidden from the next revwalk, so a commit shared between branches is only
+    /// extracted once, under the first branch that reaches it. A repository
+    /// without any commit has nothing to walk and is an error.
+    pub fn build(
+        path: PathBuf,
+        branch_refs: &[(BranchName, String)],
+    ) -> Result<HashMap<BranchName, Committers>, Error> {
+        let repo = git2::Repository::open(path.as_path())?;
+        if repo.is_empty()? {
+            return Err(Error::EmptyRepository(path.display().to_string()));
+        }
 
         info!(
             "[{:?}][{:?}] Build log by revwalking",
@@ -21,17 +33,55 @@ impl Log {
             &path
         );
 
-        let walk: Vec<Oid> = revwalk.into_iter().map(|id| id.unwrap()).collect();
-        let walk_iter_count = walk.len();
+        let mut walked_tips: Vec<Oid> = Vec::with_capacity(branch_refs.len());
+        let mut branch_data = HashMap::with_capacity(branch_refs.len());
+
+        for (branch, reference) in branch_refs {
+            let tip = repo.refname_to_id(reference)?;
+
+            let mut revwalk = repo.revwalk()?;
+            revwalk.set_sorting(Sort::TIME)?;
+            revwalk.push(tip)?;
+            for walked_tip in &walked_tips {
+                revwalk.hide(*walked_tip)?;
+            }
+
+            let walk = revwalk.collect::<Result<Vec<Oid>, _>>()?;
+            let walk_iter_count = walk.len();
+
+            let mut repo_data = Committers::new();
+            for (i, commit_id) in walk.into_iter().enumerate() {
+                if i % 1000 == 0 {
+                    info!("[{}] Revwalk iteration {}/{} ", branch, i, walk_iter_count);
+                }
 
-        for (i, commit_id) in walk.into_iter().enumerate() {
-            if i % 1000 == 0 {
-                info!("Revwalk iteration {}/{} ", i, walk_iter_count);
+                repo_data.update(&repo, commit_id)?;
             }
 
-            repo_data.update(&repo, commit_id);
+            walked_tips.push(tip);
+            branch_data.insert(branch.clone(), repo_data);
+        }
+
+        Ok(branch_data)
+    }
+
+    /// Collect tagger identities from every annotated tag under `refs/tags`.
+    /// Lightweight tags carry no signature and are skipped.
+    pub fn build_tags(path: PathBuf) -> Result<Taggers, Error> {
+        let repo = git2::Repository::open(path.as_path())?;
+        let mut taggers = Taggers::new();
+
+        info!("[{:?}][{:?}] Build tags log", thread::current().id(), &path);
+
+        for reference in repo.references_glob("refs/tags/*")?.flatten() {
+            if let Ok(tag) = reference.peel_to_tag() {
+                let tag_name = TagName(tag.name().unwrap_or("").to_string());
+                if let Some(signature) = tag.tagger() {
+                    taggers.update(&signature, tag_name);
+                }
+            }
         }
 
-        repo_data
+        Ok(taggers)
     }
 }
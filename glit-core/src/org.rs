@@ -1,11 +1,24 @@
 use ahash::RandomState;
 use async_trait::async_trait;
 use dashmap::DashMap;
-use reqwest::{Client, Url};
-use scraper::{Html, Selector};
+use reqwest::Url;
+use scraper::Html;
 use serde::Serialize;
-
-use crate::{config::OrgConfig, repo::Repository, types::RepoName, ExtractLog, Factory};
+use std::{path::PathBuf, sync::Arc};
+use tracing::error;
+
+use crate::{
+    checkpoint::Checkpoint,
+    config::{CloneFilter, ConcurrencyConfig, CredentialsConfig, OrgConfig, TlsConfig},
+    error::{Error, Failure},
+    forge::{Account, Owner},
+    http::HttpClient,
+    parse_selector,
+    repo::Repository,
+    scan::Inspect,
+    types::{RepoName, Stage},
+    ExtractLog, Factory,
+};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Org {
@@ -14,98 +27,150 @@ pub struct Org {
     pub url: Url,
     pub repo_count: usize,
     #[serde(skip)]
-    pub pages_urls: Vec<Url>,
+    pub repositories: Vec<Url>,
     #[serde(skip)]
     pub all_branches: bool,
+    #[serde(skip)]
+    pub filter: Option<CloneFilter>,
+    #[serde(skip)]
+    pub tls: TlsConfig,
+    #[serde(skip)]
+    pub credentials: CredentialsConfig,
+    #[serde(skip)]
+    pub concurrency: ConcurrencyConfig,
+    #[serde(skip)]
+    pub workdir: PathBuf,
+    #[serde(skip)]
+    pub cache: Option<PathBuf>,
+    #[serde(skip)]
+    pub checkpoint: Option<Arc<Checkpoint>>,
     pub repositories_data: DashMap<RepoName, Repository, RandomState>,
+    /// Repositories that could not be listed, cloned, walked or cleaned up
+    pub failures: DashMap<RepoName, Failure, RandomState>,
 }
 
 pub struct OrgFactory {
-    url: Url,
-    name: String,
-    page_url: Url,
+    account: Account,
     all_branches: bool,
+    filter: Option<CloneFilter>,
+    credentials: CredentialsConfig,
+    concurrency: ConcurrencyConfig,
+    tls: TlsConfig,
+    workdir: PathBuf,
+    cache: Option<PathBuf>,
+    checkpoint: Option<Arc<Checkpoint>>,
 }
 
 impl OrgFactory {
-    pub fn with_config(org_config: OrgConfig) -> Self {
+    pub fn with_config(org_config: OrgConfig) -> Result<Self, Error> {
         // CLI param
-        let url = org_config.url;
         let all_branches: bool = org_config.all_branches;
+        let filter = org_config.filter;
+        let mut credentials = org_config.credentials;
+        let concurrency = org_config.concurrency;
+        let tls = org_config.tls;
+        let workdir = org_config.workdir;
+        let cache = org_config.cache;
+        let checkpoint = org_config
+            .checkpoint
+            .as_ref()
+            .map(Checkpoint::with_config)
+            .transpose()?
+            .map(Arc::new);
 
         // Craft other param
-        let mut path_segment = url.path_segments().unwrap();
-        let name = path_segment.next().unwrap().to_string();
-
-        let host = url.host().unwrap().to_string();
-        let scheme = url.scheme();
-
-        let page_url = format!(
-            "{}://{}/orgs/{}/repositories?q=&type=source",
-            scheme, host, name
-        );
-        let page_url = Url::parse(&page_url).unwrap();
-
-        Self {
-            url,
-            name,
-            page_url,
+        let account = Account::new(
+            Owner::Org,
+            org_config.url,
+            org_config.forge,
+            org_config.api_url,
+            org_config.visibility,
+        )?;
+        credentials
+            .username
+            .get_or_insert_with(|| account.forge.token_username().to_string());
+
+        Ok(Self {
+            account,
             all_branches,
-        }
+            filter,
+            credentials,
+            concurrency,
+            tls,
+            workdir,
+            cache,
+            checkpoint,
+        })
     }
 
-    pub async fn build_with_client(self, client: &Client) -> Org {
-        let repo_count = Self::_repositories_count(client, self.page_url.clone()).await;
-        let pages_count = Self::_pages_count(repo_count);
-        let pages_urls = Self::_build_repo_links(self.page_url, repo_count, pages_count);
+    /// A listing failure is kept in the failures of the result, with the account name.
+    pub async fn build_with_client(self, client: &HttpClient) -> Org {
+        let failures = DashMap::with_hasher(RandomState::new());
+        let repositories = self
+            .account
+            .repositories::<Self>(client, self.credentials.token.clone())
+            .await
+            .unwrap_or_else(|e| {
+                error!("Listing of {} failed : {}", self.account.name(), e);
+                failures.insert(
+                    RepoName(self.account.name().to_string()),
+                    Failure::new(Stage::Listing, &e),
+                );
+                Vec::new()
+            });
+        let repo_count = repositories.len();
 
         Org {
-            name: self.name,
-            url: self.url,
+            name: self.account.name().to_string(),
+            url: self.account.url,
             repo_count,
-            pages_urls,
+            repositories,
             all_branches: self.all_branches,
+            filter: self.filter,
+            tls: self.tls,
+            credentials: self.credentials,
+            concurrency: self.concurrency,
+            workdir: self.workdir,
+            cache: self.cache,
+            checkpoint: self.checkpoint,
             repositories_data: DashMap::<_, _, RandomState>::with_capacity_and_hasher(
                 repo_count,
                 RandomState::new(),
             ),
+            failures,
         }
     }
 }
 
 #[async_trait]
 impl Factory for OrgFactory {
-    async fn _repositories_count(client: &Client, url: Url) -> usize {
-        let resp = client.get(url).send().await.unwrap();
-        let text = resp.text().await.unwrap();
+    async fn _repositories_count(client: &HttpClient, url: Url) -> Result<usize, Error> {
+        let text = client.send(client.get(url)).await?.text().await?;
 
         let parser = Html::parse_document(&text);
         let selector_repositories_count =
-            Selector::parse(r#"main > div > div > div > div > div > div > div > strong"#).unwrap();
+            parse_selector(r#"main > div > div > div > div > div > div > div > strong"#)?;
 
         let repository_count_str = parser
             .select(&selector_repositories_count)
             .next()
-            .unwrap()
+            .ok_or_else(|| Error::Parse("Repository count not found in page".to_string()))?
             .inner_html();
 
         repository_count_str
             .trim()
             .replace(',', "")
             .parse::<usize>()
-            .unwrap()
+            .map_err(|e| Error::Parse(format!("Repository count : {}", e)))
     }
 }
 
 #[async_trait]
 impl ExtractLog for Org {
-    async fn extract_log(mut self, client: &Client) -> Self {
-        let org_selector = Selector::parse(
-            r#"main > div > div > div > div > div > div > ul > li > div > div > div > h3 > a"#,
-        )
-        .unwrap();
-
-        self.repositories_data = Self::common_log_feature(&self, client, org_selector).await;
+    async fn extract_log_with(mut self, _client: &HttpClient, inspect: &mut Inspect<'_>) -> Self {
+        let (repositories_data, failures) = Self::common_log_feature(&self, inspect).await;
+        self.repositories_data = repositories_data;
+        self.failures.extend(failures);
         self
     }
 
@@ -113,6 +178,34 @@ impl ExtractLog for Org {
         self.all_branches
     }
 
+    fn get_filter(&self) -> Option<CloneFilter> {
+        self.filter
+    }
+
+    fn get_tls(&self) -> TlsConfig {
+        self.tls.clone()
+    }
+
+    fn get_credentials(&self) -> CredentialsConfig {
+        self.credentials.clone()
+    }
+
+    fn get_concurrency(&self) -> ConcurrencyConfig {
+        self.concurrency
+    }
+
+    fn get_workdir(&self) -> PathBuf {
+        self.workdir.clone()
+    }
+
+    fn get_cache(&self) -> Option<PathBuf> {
+        self.cache.clone()
+    }
+
+    fn get_checkpoint(&self) -> Option<Arc<Checkpoint>> {
+        self.checkpoint.clone()
+    }
+
     fn get_url(&self) -> Url {
         self.url.clone()
     }
@@ -121,7 +214,7 @@ impl ExtractLog for Org {
         self.repo_count
     }
 
-    fn get_pages_url(&self) -> Vec<Url> {
-        self.pages_urls.clone()
+    fn get_repositories(&self) -> Vec<Url> {
+        self.repositories.clone()
     }
 }
@@ -1,40 +1,60 @@
 use crate::{
-    config::RepositoryConfig,
+    cache::Cache,
+    config::{CloneFilter, CredentialsConfig, RepositoryConfig, RepositorySource, TlsConfig},
+    error::Error,
+    forge::ForgeKind,
     log::Log,
-    types::{AuthorName, BranchName},
+    types::{AuthorName, BranchName, Role, TagName},
 };
 use ahash::{HashMap, HashMapExt};
 use git2::{
     build::{CheckoutBuilder, RepoBuilder},
-    BranchType, Oid,
+    message_trailers_strs,
+    opts::set_extensions,
+    AutotagOption, Cred, CredentialType, FetchPrune, Oid, Signature,
 };
 use rand::distributions::{Alphanumeric, DistString};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
-    collections::BTreeMap,
-    fs::remove_dir_all,
+    collections::{BTreeMap, BTreeSet},
+    ffi::CString,
+    fs::{canonicalize, remove_dir_all},
     io::{self, Write},
+    os::raw::{c_char, c_int},
     path::{Path, PathBuf},
-    str::FromStr,
+    process::Command,
+    ptr,
+    sync::{Arc, Mutex, MutexGuard, Once, PoisonError},
     time::Instant,
 };
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use git2::{FetchOptions, Progress, RemoteCallbacks};
 
-const DEFAULT_PATH: &str = "/tmp";
+/// Answer the HTTPS credential requests of `git` from the environment, so the
+/// token never shows up in the command line.
+const CREDENTIAL_HELPER: &str = r#"credential.helper=!f() { test "$1" = get && echo "username=$GLIT_USERNAME" && echo "password=$GLIT_PASSWORD"; }; f"#;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repository {
     pub name: String,
     pub owner: String,
+    /// Repository without any commit, it has no branch
+    pub empty: bool,
     branches: Vec<BranchName>,
     #[serde(skip)]
-    clone_paths: Vec<PathBuf>,
+    clone_path: Option<PathBuf>,
+    /// Clone made by glit, removed once the log is extracted or when the last
+    /// copy of the repository is dropped
+    #[serde(skip)]
+    clone_guard: Option<Arc<CloneGuard>>,
+    /// Reference to walk for each branch, default branch first
+    #[serde(skip)]
+    branch_refs: Vec<(BranchName, String)>,
     pub branch_data: HashMap<BranchName, Committers>,
+    pub tags: Taggers,
 }
 
 struct State {
@@ -48,63 +68,204 @@ struct State {
 pub struct RepositoryFactory {
     all_branches: bool,
     branches: Vec<BranchName>,
-    url: Url,
+    source: RepositorySource,
+    filter: Option<CloneFilter>,
+    tls: TlsConfig,
+    credentials: CredentialsConfig,
+    workdir: PathBuf,
+    cache: Option<Cache>,
+}
+
+/// Credentials already offered to the remote. libgit2 asks again after each
+/// rejection, so every credential is offered once.
+#[derive(Default)]
+struct CredentialsAttempts {
+    ssh_agent: bool,
+    ssh_key: bool,
+    token: bool,
 }
 
 impl RepositoryFactory {
     pub fn with_config(repository_config: RepositoryConfig) -> Self {
-        let url = repository_config.url;
+        let source = repository_config.source;
         let all_branches: bool = repository_config.all_branches;
+        let filter = repository_config.filter;
+        let tls = repository_config.tls;
+        let credentials = repository_config.credentials;
+        let workdir = repository_config.workdir;
+        let cache = repository_config.cache.map(Cache::new);
 
         RepositoryFactory {
             all_branches,
-            url,
+            source,
             branches: Vec::<BranchName>::new(),
+            filter,
+            tls,
+            credentials,
+            workdir,
+            cache,
         }
     }
 
-    fn get_head_branch(repo: &git2::Repository) -> String {
-        let head = repo.head();
-        if let Ok(head_ref) = head {
-            head_ref
-                .name()
-                .unwrap()
-                .split('/')
-                .last()
-                .unwrap()
-                .to_string()
-        } else {
-            "".to_string()
+    /// Name of the default branch, `None` when `HEAD` points to a branch without commit.
+    fn get_head_branch(repo: &git2::Repository) -> Option<String> {
+        let head = repo.head().ok()?;
+        head.shorthand()
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+    }
+
+    /// List the branches to walk besides the default one, with their reference.
+    /// Bare clones made by libgit2 keep them as `refs/remotes/origin/*`, the ones
+    /// made by git as `refs/heads/*`. Other remotes of a local repository keep
+    /// their remote name as prefix. A remote-tracking branch that diverged from
+    /// the local branch of the same name, as in a working tree, is kept as
+    /// `origin/<name>`.
+    pub fn fetch_branches(
+        repository: &git2::Repository,
+        head: &str,
+    ) -> Result<Vec<(BranchName, String)>, Error> {
+        let target = |reference: &git2::Reference| reference.peel_to_commit().ok().map(|c| c.id());
+
+        // Do not walk default branch two time
+        let mut seen = HashMap::new();
+        seen.insert(
+            BranchName(head.to_string()),
+            repository.head().ok().and_then(|head| target(&head)),
+        );
+
+        let mut branches = Vec::new();
+        for branch in repository.branches(None)? {
+            let branch = branch?.0;
+            let Some(reference) = branch.get().name() else {
+                continue;
+            };
+            let Some(remote_name) = reference
+                .strip_prefix("refs/heads/")
+                .or_else(|| reference.strip_prefix("refs/remotes/"))
+            else {
+                continue;
+            };
+            let mut name = BranchName(
+                reference
+                    .strip_prefix("refs/remotes/origin/")
+                    .unwrap_or(remote_name)
+                    .to_string(),
+            );
+            if name.0 == "HEAD" {
+                continue;
+            }
+
+            let commit = target(branch.get());
+            match seen.get(&name) {
+                Some(seen_commit) if *seen_commit == commit => continue,
+                Some(_) => name = BranchName(remote_name.to_string()),
+                None => {}
+            }
+
+            seen.insert(name.clone(), commit);
+            branches.push((name, reference.to_string()));
         }
+
+        Ok(branches)
     }
 
-    pub fn fetch_branches(repository: &git2::Repository, head: &str) -> Vec<BranchName> {
-        let mut branches = repository
-            .branches(Some(BranchType::Remote))
-            .unwrap()
-            .into_iter()
-            .map(|b| {
-                let branch = b.unwrap().0;
-                let branch_name = branch.name().unwrap().unwrap();
-                let string_branch = branch_name.split("origin/").last().unwrap().to_string();
-                BranchName(string_branch)
-            })
-            .collect::<Vec<_>>();
+    fn clone(&self, url: &Url, path: &Path) -> Result<git2::Repository, git2::Error> {
+        let https = self
+            .credentials
+            .https(ForgeKind::from_url(url).token_username());
 
-        branches.retain(|value| *value != BranchName("HEAD".to_string()));
-        branches.retain(|value| *value != BranchName(head.to_string())); // Do not clone default branch two time
+        if let Some(filter) = self.filter {
+            match self.partial_clone(url, path, filter, https.as_ref()) {
+                Ok(repo) => return Ok(repo),
+                Err(e) => {
+                    error!(
+                        "Partial clone of {} failed, fallback to full clone : {}",
+                        url,
+                        e.message()
+                    );
+                    let _ = remove_dir_all(path);
+                }
+            }
+        }
 
-        branches
+        self.full_clone(url, path, https.as_ref())
     }
 
-    pub fn prepare_branch(branches: Vec<BranchName>) -> Vec<BranchName> {
-        branches
-            .iter()
-            .map(|branch| BranchName(branch.to_string().replace('/', "_")))
-            .collect::<Vec<_>>()
+    /// libgit2 can not negotiate a filter with the server, so partial clones are
+    /// delegated to the git executable.
+    fn partial_clone(
+        &self,
+        url: &Url,
+        path: &Path,
+        filter: CloneFilter,
+        https: Option<&(String, String)>,
+    ) -> Result<git2::Repository, git2::Error> {
+        let output = self
+            .git_command(https)
+            .args(["clone", "--bare", "--quiet"])
+            .arg(format!("--filter={}", filter))
+            .arg(url.as_str())
+            .arg(path)
+            .output()
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(git2::Error::from_str(stderr.trim()));
+        }
+
+        if stderr.contains("filtering not recognized by server") {
+            info!(
+                "[{}] Partial clone not supported by server, full clone done",
+                url
+            );
+        } else {
+            info!("[{}] Partial clone with filter {}", url, filter);
+        }
+
+        git2::Repository::open_bare(path)
     }
 
-    fn clone(url: &Url, path: &Path) -> Result<git2::Repository, git2::Error> {
+    /// `git` with the TLS options and the credentials of the factory.
+    fn git_command(&self, https: Option<&(String, String)>) -> Command {
+        let mut command = Command::new("git");
+        if let Some(ca_cert) = &self.tls.ca_cert {
+            command
+                .arg("-c")
+                .arg(format!("http.sslCAInfo={}", ca_cert.display()));
+        }
+        if self.tls.insecure {
+            command.args(["-c", "http.sslVerify=false"]);
+        }
+        if let Some((username, password)) = https {
+            // Empty helper first, to drop the helpers of the user configuration
+            command
+                .args(["-c", "credential.helper=", "-c", CREDENTIAL_HELPER])
+                .env("GLIT_USERNAME", username)
+                .env("GLIT_PASSWORD", password);
+        }
+        if let Some(ssh_key) = &self.credentials.ssh_key {
+            // A passphrase can not be typed here, the full clone takes over on failure
+            command.env(
+                "GIT_SSH_COMMAND",
+                format!(
+                    "ssh -i {} -o IdentitiesOnly=yes -o BatchMode=yes",
+                    shell_quote(&ssh_key.to_string_lossy())
+                ),
+            );
+        }
+        command.env("GIT_TERMINAL_PROMPT", "0");
+
+        command
+    }
+
+    fn full_clone(
+        &self,
+        url: &Url,
+        path: &Path,
+        https: Option<&(String, String)>,
+    ) -> Result<git2::Repository, git2::Error> {
         let state = RefCell::new(State {
             progress: None,
             total: 0,
@@ -113,7 +274,7 @@ impl RepositoryFactory {
             newline: false,
         });
 
-        let mut cb = RemoteCallbacks::new();
+        let mut cb = self.remote_callbacks(https);
         cb.transfer_progress(|stats| {
             let mut state = state.borrow_mut();
 
@@ -142,157 +303,374 @@ impl RepositoryFactory {
         repo
     }
 
-    fn clone_branches(url: Url, repo_name: String, branches: Vec<BranchName>) -> Vec<PathBuf> {
-        let repo_name = repo_name.replace('-', "_");
-        branches
-            .par_iter()
-            .map(|branch| {
-                let hash_suffix = Alphanumeric.sample_string(&mut rand::thread_rng(), 6);
-                let hashed_repo_name = format!("{}_{}", repo_name, hash_suffix);
+    /// Callbacks answering the credential and certificate checks of libgit2.
+    fn remote_callbacks<'a>(&'a self, https: Option<&'a (String, String)>) -> RemoteCallbacks<'a> {
+        let mut cb = RemoteCallbacks::new();
+        let mut attempts = CredentialsAttempts::default();
+        cb.credentials(move |_, username_from_url, allowed| {
+            self.credentials(&mut attempts, https, username_from_url, allowed)
+        });
+        if self.tls.insecure {
+            cb.certificate_check(|_, host| {
+                info!("Accept certificate of {} without verification", host);
+                true
+            });
+        }
 
-                let path = format!(
-                    "{}/{}/{}",
-                    DEFAULT_PATH,
-                    hashed_repo_name,
-                    branch.to_string(),
-                );
+        cb
+    }
 
-                let branch_clone_path = PathBuf::from_str(&path).unwrap();
-                let state = RefCell::new(State {
-                    progress: None,
-                    total: 0,
-                    current: 0,
-                    path: None,
-                    newline: false,
-                });
-
-                let mut cb = RemoteCallbacks::new();
-                cb.transfer_progress(|stats| {
-                    let mut state = state.borrow_mut();
-
-                    state.progress = Some(stats.to_owned());
-                    print(&mut state);
-                    true
-                });
-
-                let mut co = CheckoutBuilder::new();
-                co.progress(|path, cur, total| {
-                    let mut state = state.borrow_mut();
-                    state.path = path.map(|p| p.to_path_buf());
-                    state.current = cur;
-                    state.total = total;
-                    print(&mut state);
-                });
-
-                let mut fo = FetchOptions::new();
-                fo.remote_callbacks(cb);
-
-                let repo = RepoBuilder::new()
-                    .bare(true)
-                    .fetch_options(fo)
-                    .with_checkout(co)
-                    .branch(&branch.to_string())
-                    .clone(url.clone().as_str(), &branch_clone_path);
-
-                match repo {
-                    Ok(_) => info!(
-                        "[{:?}] Cloning branch : {:?} at {}",
-                        repo_name, branch, path
-                    ),
-                    Err(_) => info!("Failed to clone {} with branch {:?}", repo_name, branch),
+    /// Fetch the new objects of `url` into the cached mirror at `path`, or
+    /// clone it when it is not cached yet.
+    fn update_mirror(&self, url: &Url, path: &Path) -> Result<git2::Repository, Error> {
+        if path.exists() {
+            match git2::Repository::open_bare(path) {
+                Ok(repo) => {
+                    // Stale data still beats no data, the scan goes on with the cached commits
+                    match self.fetch(url, &repo, path) {
+                        Ok(_) => Cache::touch(path)?,
+                        Err(e) => warn!(
+                            "[{}] Fetch failed, scan the cached mirror : {}",
+                            url,
+                            e.message()
+                        ),
+                    }
+                    return Ok(repo);
                 }
+                Err(e) => {
+                    warn!(
+                        "Cached mirror at {:?} can not be opened, clone it again : {}",
+                        path,
+                        e.message()
+                    );
+                    remove_dir_all(path)?;
+                }
+            }
+        }
 
-                branch_clone_path
-            })
-            .collect::<Vec<PathBuf>>()
+        // An interrupted first clone is not left in the cache
+        let clone_guard = CloneGuard::new(path.to_path_buf());
+        let repo = self.clone(url, path).map_err(Error::Clone)?;
+        clone_guard.keep();
+        Cache::touch(path)?;
+
+        Ok(repo)
     }
 
-    pub fn create(mut self) -> Repository {
-        let mut path_segments = self.url.path_segments().unwrap();
-        let owner = path_segments.next().unwrap().to_string();
-        let repo_name = path_segments.next().unwrap().to_string();
+    /// Fetch every branch and tag of the remote, and drop the deleted ones.
+    /// Partial mirrors are fetched by `git`, which keeps their filter.
+    fn fetch(&self, url: &Url, repo: &git2::Repository, path: &Path) -> Result<(), git2::Error> {
+        let https = self
+            .credentials
+            .https(ForgeKind::from_url(url).token_username());
 
-        // default location
-        let hash_suffix = Alphanumeric.sample_string(&mut rand::thread_rng(), 6);
-        let hashed_repo_name = format!("{}_{}", repo_name, hash_suffix);
-        let clone_location = PathBuf::from_str(&format!(
-            "{}/{}/{}",
-            DEFAULT_PATH, hashed_repo_name, "default"
+        let config = repo.config()?;
+        let partial = config.get_bool("remote.origin.promisor").unwrap_or(false);
+
+        if partial {
+            let output = self
+                .git_command(https.as_ref())
+                .arg("-C")
+                .arg(path)
+                .args([
+                    "fetch",
+                    "--prune",
+                    "--quiet",
+                    "origin",
+                    "+refs/heads/*:refs/heads/*",
+                    "+refs/tags/*:refs/tags/*",
+                ])
+                .output()
+                .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(git2::Error::from_str(stderr.trim()));
+            }
+        } else {
+            let mut fo = FetchOptions::new();
+            fo.remote_callbacks(self.remote_callbacks(https.as_ref()))
+                .prune(FetchPrune::On)
+                .download_tags(AutotagOption::All);
+
+            // `HEAD` points to a local branch, the remote ones of a libgit2 clone are updated too
+            let mut refspecs = vec!["+refs/heads/*:refs/heads/*".to_string()];
+            if let Ok(refspec) = config.get_string("remote.origin.fetch") {
+                refspecs.push(refspec);
+            }
+            repo.find_remote("origin")?
+                .fetch(&refspecs, Some(&mut fo), None)?;
+        }
+
+        info!("[{}] Cached mirror fetched", url);
+        Ok(())
+    }
+
+    /// SSH agent first, then the SSH key file, or the token for HTTPS remotes.
+    fn credentials(
+        &self,
+        attempts: &mut CredentialsAttempts,
+        https: Option<&(String, String)>,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !attempts.ssh_agent {
+                attempts.ssh_agent = true;
+                if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+
+            if let (Some(ssh_key), false) = (&self.credentials.ssh_key, attempts.ssh_key) {
+                attempts.ssh_key = true;
+                return Cred::ssh_key(
+                    username,
+                    None,
+                    ssh_key,
+                    self.credentials.ssh_passphrase.as_deref(),
+                );
+            }
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !attempts.token {
+            if let Some((username, password)) = https {
+                attempts.token = true;
+                return Cred::userpass_plaintext(username, password);
+            }
+        }
+
+        Err(git2::Error::from_str(
+            "No credentials accepted by the remote",
         ))
-        .unwrap();
+    }
+
+    pub fn create(self) -> Result<Repository, Error> {
+        // Partial clones and mirrors on disk need the `partialclone` extension
+        init_libgit2(&self.tls)?;
 
-        let mut clone_paths: Vec<PathBuf> = Vec::new();
-        let repo: git2::Repository = Self::clone(&self.url, clone_location.as_path()).unwrap();
-        let head = Self::get_head_branch(&repo);
-        if !head.is_empty() {
-            clone_paths.push(clone_location);
+        match self.source.clone() {
+            RepositorySource::Remote(url) => self.create_from_remote(url),
+            RepositorySource::Local(path) => self.create_from_local(path),
         }
+    }
+
+    fn create_from_remote(self, url: Url) -> Result<Repository, Error> {
+        // `owner/name`, or `group/subgroup/name` on GitLab
+        let mut path_segments = url
+            .path_segments()
+            .ok_or_else(|| Error::Parse(format!("No repository path in {}", url)))?
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+        let repo_name = path_segments
+            .pop()
+            .ok_or_else(|| Error::Parse(format!("No repository name in {}", url)))?
+            .trim_end_matches(".git")
+            .to_string();
+        let owner = path_segments.join("/");
+
+        if let Some(cache) = &self.cache {
+            let mirror_path = cache.mirror_path(&url);
+            let repo = self.update_mirror(&url, &mirror_path)?;
+            return self.build(repo_name, owner, &repo, mirror_path, None);
+        }
+
+        // The guard deletes the clone on any early return
+        let hash_suffix = Alphanumeric.sample_string(&mut rand::thread_rng(), 6);
+        let hashed_repo_name = format!("{}_{}", repo_name, hash_suffix);
+        let clone_guard = CloneGuard::new(self.workdir.join(hashed_repo_name));
+        let clone_location = clone_guard.path().join("default");
+
+        // A single bare clone holds every remote branch: they are walked from there
+        let repo = self
+            .clone(&url, clone_location.as_path())
+            .map_err(Error::Clone)?;
+
+        self.build(repo_name, owner, &repo, clone_location, Some(clone_guard))
+    }
+
+    fn create_from_local(self, path: PathBuf) -> Result<Repository, Error> {
+        let path = canonicalize(path)?;
 
-        // Clone all branches
-        if self.all_branches {
-            let mut branches = Self::fetch_branches(&repo, &head);
-            //let prepared_branch = Self::prepare_branch(branches.clone());
+        let repo = git2::Repository::open(&path).map_err(Error::Clone)?;
 
-            let paths = Self::clone_branches(self.url.clone(), repo_name.clone(), branches.clone());
+        // `repo.path()` is the `.git` folder of a working tree, or `name.git` for a mirror
+        let root = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+        let repo_name = file_name(&root).trim_end_matches(".git").to_string();
+        let owner = root.parent().map(file_name).unwrap_or_default();
 
-            branches.push(BranchName(head));
-            self.branches = branches.clone();
+        info!("[{}] Open local repository at {:?}", repo_name, &path);
 
-            clone_paths.extend(paths);
+        self.build(repo_name, owner, &repo, path, None)
+    }
+
+    fn build(
+        mut self,
+        name: String,
+        owner: String,
+        repo: &git2::Repository,
+        path: PathBuf,
+        clone_guard: Option<CloneGuard>,
+    ) -> Result<Repository, Error> {
+        let empty = repo.is_empty()?;
+        if empty {
+            info!("[{}] Empty repository, nothing to walk", name);
         }
-        // Clone only default branch
-        else {
-            self.branches = vec![BranchName(head)];
+        let head = Self::get_head_branch(repo);
+
+        let mut branch_refs = Vec::new();
+        if let Some(head) = &head {
+            branch_refs.push((BranchName(head.clone()), "HEAD".to_string()));
         }
+        // Without a default branch, the other ones are the only commits to walk
+        if !empty && (self.all_branches || head.is_none()) {
+            branch_refs.extend(Self::fetch_branches(repo, head.as_deref().unwrap_or(""))?);
+        }
+        self.branches = branch_refs
+            .iter()
+            .map(|(branch, _)| branch.clone())
+            .collect();
 
-        Repository {
-            name: repo_name,
+        Ok(Repository {
+            name,
             owner,
+            empty,
             branches: self.branches.clone(),
-            clone_paths,
+            clone_path: Some(path),
+            clone_guard: clone_guard.map(Arc::new),
+            branch_refs,
             branch_data: HashMap::new(),
-        }
+            tags: Taggers::new(),
+        })
     }
 }
 
 impl Repository {
-    pub fn extract_log(mut self) -> Repository {
-        self.branch_data = self
-            .branches
-            .clone()
-            .into_iter()
-            .zip(self.clone_paths.clone())
-            .map(|(br, pt)| {
-                let t1 = Instant::now();
-                let repo_data = Log::build(pt.clone());
-                println!("Build log Time : {:?}", t1.elapsed());
-
-                let remove_path = pt.parent().unwrap();
-                let removal = remove_dir_all(remove_path);
-                match removal {
-                    Ok(_) => info!("Cleaning - Delete folder at {:?}", &remove_path),
-                    Err(_) => error!("Failed to delete at {:?}", &remove_path),
-                }
+    /// `owner/name`, or `group/subgroup/name` on GitLab.
+    pub fn full_name(&self) -> String {
+        if self.owner.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}/{}", self.owner, self.name)
+        }
+    }
 
-                (br, repo_data)
-            })
-            .collect::<HashMap<_, _>>();
+    /// Walked branches, default branch first.
+    pub fn get_branches(&self) -> &[BranchName] {
+        &self.branches
+    }
 
-        self
+    /// Walk the clone, then delete it when glit made it, whether the walk succeeded or not.
+    pub fn extract_log(mut self) -> Result<Repository, Error> {
+        let walk = self.walk();
+        let cleanup = self.cleanup();
+        walk.and(cleanup)?;
+
+        Ok(self)
+    }
+
+    /// Fill the branch and tag data from the clone. An empty repository is left as is.
+    pub fn walk(&mut self) -> Result<(), Error> {
+        if self.empty {
+            return Ok(());
+        }
+
+        if let Some(clone_path) = &self.clone_path {
+            let t1 = Instant::now();
+            self.branch_data = Log::build(clone_path.clone(), &self.branch_refs)?;
+            self.tags = Log::build_tags(clone_path.clone())?;
+            println!("Build log Time : {:?}", t1.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// Delete the clone when glit made it. A repository on disk is left untouched.
+    pub fn cleanup(&mut self) -> Result<(), Error> {
+        self.clone_path = None;
+        // Copies of the repository still sharing the clone delete it when dropped
+        match self.clone_guard.take().map(Arc::try_unwrap) {
+            Some(Ok(clone_guard)) => Ok(clone_guard.remove()?),
+            _ => Ok(()),
+        }
     }
 }
 
 type Mail = String;
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Commit {
+    pub id: String,
+    pub roles: BTreeSet<Role>,
+    /// Time of the first signature seen for the identity, the commit time for
+    /// a trailer, in seconds since the epoch
+    #[serde(default)]
+    pub time: i64,
+}
+
+impl Commit {
+    pub fn new(id: String, role: Role, time: i64) -> Self {
+        Self {
+            id,
+            roles: BTreeSet::from([role]),
+            time,
+        }
+    }
+}
+
+/// Every commit a mail was seen on, and the union of the roles it held.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MailEntry {
+    pub roles: BTreeSet<Role>,
+    pub commits: Vec<Commit>,
+}
+
+impl MailEntry {
+    pub fn new(commit_id: String, role: Role, time: i64) -> Self {
+        Self {
+            roles: BTreeSet::from([role]),
+            commits: vec![Commit::new(commit_id, role, time)],
+        }
+    }
+
+    /// Time of the oldest commit, `None` without commit.
+    pub fn first_seen(&self) -> Option<i64> {
+        self.commits.iter().map(|commit| commit.time).min()
+    }
+
+    /// Time of the newest commit, `None` without commit.
+    pub fn last_seen(&self) -> Option<i64> {
+        self.commits.iter().map(|commit| commit.time).max()
+    }
+
+    fn push(&mut self, commit_id: String, role: Role, time: i64) {
+        self.roles.insert(role);
+
+        // Author and committer are usually the same identity: keep a single commit entry
+        match self.commits.last_mut() {
+            Some(commit) if commit.id == commit_id => {
+                commit.roles.insert(role);
+            }
+            _ => self.commits.push(Commit::new(commit_id, role, time)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Committer {
-    pub mails: BTreeMap<Mail, Vec<Mail>>,
+    pub mails: BTreeMap<Mail, MailEntry>,
 }
 
 impl Committer {
-    pub fn new(mail: Mail, commit_id: String) -> Self {
+    pub fn new(mail: Mail, commit_id: String, role: Role, time: i64) -> Self {
         let mut commits_for_mail = BTreeMap::new();
-        commits_for_mail.insert(mail, vec![commit_id]);
+        commits_for_mail.insert(mail, MailEntry::new(commit_id, role, time));
 
         Self {
             mails: commits_for_mail,
@@ -318,12 +696,47 @@ impl Committers {
         }
     }
 
-    pub fn update(&mut self, repo: &git2::Repository, commit_id: Oid) -> &Self {
-        let commit = repo.find_commit(commit_id).unwrap();
-        let commit_sigature = commit.author();
-        let author: AuthorName = AuthorName(commit_sigature.name().unwrap_or("").to_string());
-        let mail = commit_sigature.email().unwrap_or("").to_string();
+    pub fn update(&mut self, repo: &git2::Repository, commit_id: Oid) -> Result<&Self, Error> {
+        let commit = repo.find_commit(commit_id)?;
+
+        self.insert_signature(&commit.author(), commit_id, Role::Author);
+        self.insert_signature(&commit.committer(), commit_id, Role::Committer);
+
+        if let Some(message) = commit.message() {
+            self.insert_trailers(message, commit_id, commit.time().seconds());
+        }
+
+        Ok(self)
+    }
+
+    fn insert_trailers(&mut self, message: &str, commit_id: Oid, time: i64) {
+        let trailers = match message_trailers_strs(message) {
+            Ok(trailers) => trailers,
+            Err(_) => return,
+        };
+
+        for (key, value) in trailers.iter() {
+            let role = match Role::from_trailer(key) {
+                Some(role) => role,
+                None => continue,
+            };
+
+            if let Some((author, mail)) = parse_identity(value) {
+                self.insert(author, mail, commit_id.to_string(), role, time);
+            }
+        }
+    }
+
+    fn insert_signature(&mut self, signature: &Signature, commit_id: Oid, role: Role) {
+        let author: AuthorName = AuthorName(signature.name().unwrap_or("").to_string());
+        let mail = signature.email().unwrap_or("").to_string();
+
+        let time = signature.when().seconds();
 
+        self.insert(author, mail, commit_id.to_string(), role, time);
+    }
+
+    fn insert(&mut self, author: AuthorName, mail: Mail, commit_id: String, role: Role, time: i64) {
         self.committers
             .entry(author)
             .and_modify(|committer| {
@@ -331,55 +744,249 @@ impl Committers {
                 committer
                     .mails
                     .entry(mail.clone())
-                    .and_modify(|commit_ids| {
+                    .and_modify(|mail_entry| {
                         // Mail Key exist
-                        commit_ids.push(commit_id.to_string());
+                        mail_entry.push(commit_id.clone(), role, time);
                     })
                     .or_insert_with(||
                         // Mail Key do not exist
-                        vec![commit_id.to_string()]);
+                        MailEntry::new(commit_id.clone(), role, time));
             })
             .or_insert_with(||
                 // Author Key do not exist
-                Committer::new(mail, commit_id.to_string()));
-
-        // A little bit faster but not cleaner
-        //let committer = Committer::new(mail.clone(), commit_id.to_string());
-        //if self.committers.contains_key(&author) {
-        //    let mut existing_commiter = self.committers.get_mut(&author).unwrap().to_owned();
-        //
-        //    if !existing_commiter.mails.contains_key(&mail) {
-        //        existing_commiter.mails.insert(mail.clone(), vec![]);
-        //
-        //        self.committers.insert(author.clone(), existing_commiter);
-        //    }
-        //
-        //    // Update commit_id list
-        //    let mut actual_committer = self.committers.get_mut(&author).unwrap().to_owned();
-        //    let mut commit_ids = actual_committer.mails.get_mut(&mail).unwrap().to_owned();
-        //
-        //    commit_ids.push(commit_id.to_string());
-        //    actual_committer.mails.insert(mail, commit_ids);
-        //
-        //    // insert modified version of commiter
-        //    self.committers.insert(author, actual_committer);
-        //} else {
-        //    self.committers.insert(author.clone(), committer);
-        //}
+                Committer::new(mail, commit_id, role, time));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Tagger {
+    pub mails: BTreeMap<Mail, Vec<TagName>>,
+}
+
+impl Tagger {
+    pub fn new(mail: Mail, tag_name: TagName) -> Self {
+        let mut tags_for_mail = BTreeMap::new();
+        tags_for_mail.insert(mail, vec![tag_name]);
+
+        Self {
+            mails: tags_for_mail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Taggers {
+    pub taggers: HashMap<AuthorName, Tagger>,
+}
+
+impl Default for Taggers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Taggers {
+    pub fn new() -> Self {
+        Self {
+            taggers: HashMap::<AuthorName, Tagger>::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.taggers.is_empty()
+    }
+
+    pub fn update(&mut self, signature: &Signature, tag_name: TagName) -> &Self {
+        let author: AuthorName = AuthorName(signature.name().unwrap_or("").to_string());
+        let mail = signature.email().unwrap_or("").to_string();
+
+        self.taggers
+            .entry(author)
+            .and_modify(|tagger| {
+                tagger
+                    .mails
+                    .entry(mail.clone())
+                    .and_modify(|tag_names| tag_names.push(tag_name.clone()))
+                    .or_insert_with(|| vec![tag_name.clone()]);
+            })
+            .or_insert_with(|| Tagger::new(mail, tag_name));
 
         self
     }
 }
 
+/// Set the process-wide libgit2 options: the `partialclone` extension, and the
+/// CA certificate of `tls` trusted by every clone and fetch. These options are
+/// not thread-safe, so a scan sets them before its clone and walk pools start,
+/// and a single repository before it is cloned or opened. Later calls with the
+/// same settings do nothing.
+pub fn init_libgit2(tls: &TlsConfig) -> Result<(), Error> {
+    allow_partial_clone();
+    match &tls.ca_cert {
+        Some(ca_cert) => set_ssl_cert_file(ca_cert),
+        None => Ok(()),
+    }
+}
+
+/// Partial clones declare the `partialclone` repository extension, which libgit2
+/// refuses by default. Missing objects are only trees and blobs, never read here.
+pub(crate) fn allow_partial_clone() {
+    static PARTIAL_CLONE: Once = Once::new();
+    PARTIAL_CLONE.call_once(|| {
+        // Safety: the extension list is process-wide and not synchronized. The
+        // first call comes from `init_libgit2` before a scan starts its pools,
+        // or from the main thread before a single repository or the cache is
+        // opened, so no other thread of glit uses libgit2 at that point
+        if let Err(e) = unsafe { set_extensions(&["partialclone"]) } {
+            error!("Failed to enable partialclone extension : {}", e.message());
+        }
+    });
+}
+
+/// Trust `ca_cert` for the clones made by libgit2, on top of the system certificates.
+fn set_ssl_cert_file(ca_cert: &Path) -> Result<(), Error> {
+    static CA_CERT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+    let mut current = CA_CERT.lock().unwrap_or_else(PoisonError::into_inner);
+    if current.as_deref() == Some(ca_cert) {
+        return Ok(());
+    }
+
+    let file = CString::new(ca_cert.to_string_lossy().as_bytes())
+        .map_err(|_| Error::Parse(format!("Invalid certificate path {:?}", ca_cert)))?;
+
+    libgit2_sys::init();
+    // Safety: libgit2 is initialized and copies the path. The option is
+    // process-wide, `init_libgit2` sets it before any clone starts
+    let ret = unsafe {
+        libgit2_sys::git_libgit2_opts(
+            libgit2_sys::GIT_OPT_SET_SSL_CERT_LOCATIONS as c_int,
+            file.as_ptr(),
+            ptr::null::<c_char>(),
+        )
+    };
+    if ret < 0 {
+        let message = match git2::Error::last_error(ret) {
+            Some(e) => format!("Failed to load certificate {:?} : {}", ca_cert, e.message()),
+            None => format!("Failed to load certificate {:?}", ca_cert),
+        };
+        return Err(Error::Git(git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Ssl,
+            message,
+        )));
+    }
+    *current = Some(ca_cert.to_path_buf());
+
+    Ok(())
+}
+
+/// Folders of the clones not deleted yet, removed by `remove_live_clones`.
+static LIVE_CLONES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Folder of a temporary clone, deleted when the guard is dropped so that a
+/// failed or panicking extraction does not leave it behind.
+#[derive(Debug)]
+pub struct CloneGuard {
+    path: Option<PathBuf>,
+}
+
+impl CloneGuard {
+    pub fn new(path: PathBuf) -> Self {
+        live_clones().push(path.clone());
+        Self { path: Some(path) }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().unwrap_or_else(|| Path::new(""))
+    }
+
+    /// Keep the folder, for a clone that outlives the scan.
+    pub fn keep(mut self) -> PathBuf {
+        let path = self.path.take().unwrap_or_default();
+        live_clones().retain(|live| *live != path);
+        path
+    }
+
+    /// Delete the folder now, reporting the failure the drop would ignore.
+    pub fn remove(mut self) -> io::Result<()> {
+        match self.path.take() {
+            Some(path) => remove_clone(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CloneGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = remove_clone(&path);
+        }
+    }
+}
+
+/// Delete every clone still on disk, for an interrupted process whose guards
+/// will never be dropped.
+pub fn remove_live_clones() {
+    let paths = std::mem::take(&mut *live_clones());
+    for path in paths {
+        let _ = remove_clone(&path);
+    }
+}
+
+fn live_clones() -> MutexGuard<'static, Vec<PathBuf>> {
+    LIVE_CLONES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Delete the folder of a temporary clone.
+fn remove_clone(remove_path: &Path) -> io::Result<()> {
+    live_clones().retain(|path| path != remove_path);
+    if !remove_path.exists() {
+        return Ok(());
+    }
+
+    match remove_dir_all(remove_path) {
+        Ok(_) => {
+            info!("Cleaning - Delete folder at {:?}", &remove_path);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to delete at {:?}", &remove_path);
+            Err(e)
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Single-quote `value` for a POSIX shell, a quote inside it being closed,
+/// escaped and reopened.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Split a trailer value like `Jane Doe <jane@example.com>` into name and mail.
+fn parse_identity(value: &str) -> Option<(AuthorName, Mail)> {
+    let (name, rest) = value.rsplit_once('<')?;
+    let (mail, _) = rest.split_once('>')?;
+    let mail = mail.trim();
+
+    if mail.is_empty() {
+        return None;
+    }
+
+    Some((AuthorName(name.trim().to_string()), mail.to_string()))
+}
+
 fn print(state: &mut State) {
     let stats = state.progress.as_ref().unwrap();
     let network_pct = (100 * stats.received_objects()) / stats.total_objects();
     let index_pct = (100 * stats.indexed_objects()) / stats.total_objects();
-    let co_pct = if state.total > 0 {
-        (100 * state.current) / state.total
-    } else {
-        0
-    };
+    let co_pct = (100 * state.current).checked_div(state.total).unwrap_or(0);
     let kbytes = stats.received_bytes() / 1024;
     if stats.received_objects() == stats.total_objects() {
         if !state.newline {
@@ -414,3 +1021,368 @@ fn print(state: &mut State) {
     }
     io::stdout().flush().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Empty repository in a folder of its own for each test.
+    fn init(name: &str) -> (PathBuf, git2::Repository) {
+        let path = env::temp_dir().join(format!("glit-repo-{}-{}", name, process::id()));
+        let _ = remove_dir_all(&path);
+        let repo = git2::Repository::init(&path).unwrap();
+
+        (path, repo)
+    }
+
+    /// Commit of `author` with `message` on top of `parents`, `reference` being moved to it.
+    fn commit(
+        repo: &git2::Repository,
+        reference: &str,
+        author: &str,
+        message: &str,
+        parents: &[Oid],
+    ) -> Oid {
+        let signature = Signature::now(author, &format!("{}@example.com", author)).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parents = parents
+            .iter()
+            .map(|id| repo.find_commit(*id).unwrap())
+            .collect::<Vec<_>>();
+
+        repo.commit(
+            Some(reference),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    fn identity(name: &str, mail: &str) -> Option<(AuthorName, Mail)> {
+        Some((AuthorName(name.to_string()), mail.to_string()))
+    }
+
+    #[test]
+    fn shell_quote_escapes_quotes() {
+        assert_eq!(shell_quote("/home/jane/.ssh/id"), "'/home/jane/.ssh/id'");
+        assert_eq!(shell_quote("/keys/a b"), "'/keys/a b'");
+        assert_eq!(shell_quote("/keys/jane's"), r"'/keys/jane'\''s'");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+    }
+
+    #[test]
+    fn credentials_are_offered_once_each() {
+        let mut config = RepositoryConfig::local(env::temp_dir());
+        config.credentials.ssh_key = Some(PathBuf::from("/keys/id_ed25519"));
+        let factory = RepositoryFactory::with_config(config);
+        let https = ("x-access-token".to_string(), "ghp_secret".to_string());
+
+        // SSH agent, then the key file
+        let mut attempts = CredentialsAttempts::default();
+        let mut offer = |https, allowed| {
+            let offered = factory.credentials(&mut attempts, https, None, allowed);
+            (
+                offered.is_ok(),
+                attempts.ssh_agent,
+                attempts.ssh_key,
+                attempts.token,
+            )
+        };
+        assert_eq!(
+            offer(None, CredentialType::SSH_KEY),
+            (true, true, false, false)
+        );
+        assert_eq!(
+            offer(None, CredentialType::SSH_KEY),
+            (true, true, true, false)
+        );
+        assert_eq!(
+            offer(None, CredentialType::SSH_KEY),
+            (false, true, true, false)
+        );
+
+        // The token of an HTTPS remote
+        let mut attempts = CredentialsAttempts::default();
+        let mut offer = |allowed| {
+            let offered = factory.credentials(&mut attempts, Some(&https), None, allowed);
+            (
+                offered.is_ok(),
+                attempts.ssh_agent,
+                attempts.ssh_key,
+                attempts.token,
+            )
+        };
+        let allowed = CredentialType::USER_PASS_PLAINTEXT;
+        assert_eq!(offer(allowed), (true, false, false, true));
+        assert_eq!(offer(allowed), (false, false, false, true));
+    }
+
+    #[test]
+    fn unreadable_certificates_are_git_errors() {
+        let path = env::temp_dir().join(format!("glit-repo-ca-{}.pem", process::id()));
+        fs::write(&path, "not a certificate").unwrap();
+
+        let error = set_ssl_cert_file(&path).unwrap_err();
+        assert!(matches!(&error, Error::Git(e) if e.class() == git2::ErrorClass::Ssl));
+        assert!(error.to_string().contains("glit-repo-ca-"), "{}", error);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_identity_of_trailer_values() {
+        assert_eq!(
+            parse_identity("Jane Doe <jane@example.com>"),
+            identity("Jane Doe", "jane@example.com")
+        );
+        assert_eq!(
+            parse_identity("  Jane Doe   <  jane@example.com >  "),
+            identity("Jane Doe", "jane@example.com")
+        );
+        // The last bracket holds the mail
+        assert_eq!(
+            parse_identity("Jane <jd> Doe <jane@example.com>"),
+            identity("Jane <jd> Doe", "jane@example.com")
+        );
+        assert_eq!(
+            parse_identity("<jane@example.com>"),
+            identity("", "jane@example.com")
+        );
+
+        assert_eq!(parse_identity("jane@example.com"), None);
+        assert_eq!(parse_identity("Jane Doe <jane@example.com"), None);
+        assert_eq!(parse_identity("Jane Doe jane@example.com>"), None);
+        assert_eq!(parse_identity("Jane Doe <>"), None);
+        assert_eq!(parse_identity("Jane Doe <  >"), None);
+        assert_eq!(parse_identity(""), None);
+    }
+
+    #[test]
+    fn trailer_keys_ignore_case() {
+        assert_eq!(Role::from_trailer("Signed-off-by"), Some(Role::SignedOffBy));
+        assert_eq!(
+            Role::from_trailer("CO-AUTHORED-BY"),
+            Some(Role::CoAuthoredBy)
+        );
+        assert_eq!(Role::from_trailer(" reviewed-by "), Some(Role::ReviewedBy));
+        assert_eq!(Role::from_trailer("Co-authored"), None);
+        assert_eq!(Role::from_trailer("Fixes"), None);
+    }
+
+    #[test]
+    fn trailers_of_a_commit_are_identities() {
+        let (path, repo) = init("trailers");
+        let message = "Fix the widget\n\n\
+                       Body of the commit.\n\n\
+                       Co-Authored-By: Carol <carol@example.net>\n\
+                       signed-off-by: Alice <alice@example.com>\n\
+                       Reviewed-by: rob@example.org\n\
+                       Acked-by: Nobody <>\n\
+                       Fixes: #42\n";
+        let id = commit(&repo, "HEAD", "alice", message, &[]);
+
+        let mut committers = Committers::new();
+        committers.update(&repo, id).unwrap();
+
+        let roles = |author: &str, mail: &str| {
+            committers.committers[&AuthorName(author.to_string())].mails[mail]
+                .roles
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            roles("alice", "alice@example.com"),
+            [Role::Author, Role::Committer]
+        );
+        assert_eq!(roles("Alice", "alice@example.com"), [Role::SignedOffBy]);
+        assert_eq!(roles("Carol", "carol@example.net"), [Role::CoAuthoredBy]);
+        // Trailers without a bracketed mail, or with an empty one, are skipped
+        assert_eq!(committers.committers.len(), 3);
+
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn author_and_committer_are_distinct_identities() {
+        let (path, repo) = init("committer");
+        let author =
+            Signature::new("Alice", "alice@example.com", &git2::Time::new(100, 0)).unwrap();
+        let committer = Signature::new("Max", "max@example.org", &git2::Time::new(200, 0)).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let id = repo
+            .commit(Some("HEAD"), &author, &committer, "init", &tree, &[])
+            .unwrap();
+
+        let mut committers = Committers::new();
+        committers.update(&repo, id).unwrap();
+
+        assert_eq!(committers.committers.len(), 2);
+        let entry = |name: &str, mail: &str| {
+            committers.committers[&AuthorName(name.to_string())].mails[mail].clone()
+        };
+        let alice = entry("Alice", "alice@example.com");
+        assert_eq!(alice.roles, BTreeSet::from([Role::Author]));
+        assert_eq!(
+            alice.commits,
+            [Commit::new(id.to_string(), Role::Author, 100)]
+        );
+        // A committer-only identity is dated by the commit time
+        let max = entry("Max", "max@example.org");
+        assert_eq!(max.roles, BTreeSet::from([Role::Committer]));
+        assert_eq!(
+            max.commits,
+            [Commit::new(id.to_string(), Role::Committer, 200)]
+        );
+
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn only_annotated_tags_have_a_tagger() {
+        let (path, repo) = init("tags");
+        let id = commit(&repo, "HEAD", "alice", "init", &[]);
+        let target = repo.find_object(id, None).unwrap();
+
+        let tagger = Signature::now("Rel Eng", "release@example.com").unwrap();
+        repo.tag("v1.0", &target, &tagger, "Release 1.0", false)
+            .unwrap();
+        repo.tag_lightweight("nightly", &target, false).unwrap();
+
+        let taggers = Log::build_tags(path.clone()).unwrap();
+
+        assert_eq!(taggers.taggers.len(), 1);
+        let mails = &taggers.taggers[&AuthorName("Rel Eng".to_string())].mails;
+        assert_eq!(mails["release@example.com"], [TagName("v1.0".to_string())]);
+
+        remove_dir_all(&path).unwrap();
+    }
+
+    /// Ids of the commits walked for `branch`.
+    fn commit_ids(branch_data: &HashMap<BranchName, Committers>, branch: &str) -> BTreeSet<Oid> {
+        branch_data[&BranchName(branch.to_string())]
+            .committers
+            .values()
+            .flat_map(|committer| committer.mails.values())
+            .flat_map(|entry| entry.commits.iter())
+            .map(|commit| Oid::from_str(&commit.id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn shared_commits_are_walked_under_the_first_branch() {
+        let (path, repo) = init("branches");
+        let base = commit(&repo, "refs/heads/main", "alice", "base", &[]);
+        let fix = commit(&repo, "refs/heads/main", "alice", "fix", &[base]);
+        let feature = commit(&repo, "refs/heads/feature", "bob", "feature", &[base]);
+        let follow_up = commit(&repo, "refs/heads/feature", "carol", "more", &[feature]);
+        // Already merged in main, it has no commit of its own
+        repo.reference("refs/heads/old", base, false, "old")
+            .unwrap();
+
+        let branch_refs = ["main", "feature", "old"].map(|branch| {
+            (
+                BranchName(branch.to_string()),
+                format!("refs/heads/{}", branch),
+            )
+        });
+        let branch_data = Log::build(path.clone(), &branch_refs).unwrap();
+
+        assert_eq!(
+            commit_ids(&branch_data, "main"),
+            BTreeSet::from([base, fix])
+        );
+        assert_eq!(
+            commit_ids(&branch_data, "feature"),
+            BTreeSet::from([feature, follow_up])
+        );
+        assert!(commit_ids(&branch_data, "old").is_empty());
+
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn diverged_remote_branches_of_a_working_tree_are_kept() {
+        let (path, repo) = init("remotes");
+        repo.set_head("refs/heads/main").unwrap();
+        let base = commit(&repo, "HEAD", "alice", "base", &[]);
+        let fix = commit(&repo, "HEAD", "alice", "fix", &[base]);
+        let upstream = commit(&repo, "refs/remotes/origin/main", "bob", "up", &[base]);
+        let feature = commit(&repo, "refs/heads/feature", "carol", "feature", &[base]);
+        repo.reference("refs/remotes/origin/feature", feature, false, "fetch")
+            .unwrap();
+        let topic = commit(&repo, "refs/remotes/origin/topic", "dave", "topic", &[base]);
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "clone",
+        )
+        .unwrap();
+
+        let repository = RepositoryFactory::with_config(RepositoryConfig::local(path.clone()))
+            .create()
+            .unwrap()
+            .extract_log()
+            .unwrap();
+
+        let branches = repository
+            .get_branches()
+            .iter()
+            .map(|branch| branch.0.as_str())
+            .collect::<Vec<_>>();
+        // `origin/feature` is the local `feature`, `origin/topic` has no local branch
+        assert_eq!(branches, ["main", "feature", "origin/main", "topic"]);
+        let branch_data = &repository.branch_data;
+        assert_eq!(commit_ids(branch_data, "main"), BTreeSet::from([base, fix]));
+        assert_eq!(
+            commit_ids(branch_data, "origin/main"),
+            BTreeSet::from([upstream])
+        );
+        assert_eq!(commit_ids(branch_data, "topic"), BTreeSet::from([topic]));
+
+        // The repository on disk is left untouched
+        assert!(path.join(".git").is_dir());
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn empty_repositories_have_no_branch() {
+        let path = env::temp_dir().join(format!("glit-repo-empty-{}", process::id()));
+        let _ = remove_dir_all(&path);
+        git2::Repository::init_bare(path.join("acme/empty.git")).unwrap();
+        let url = Url::from_directory_path(path.join("acme/empty.git")).unwrap();
+        let workdir = path.join("workdir");
+        fs::create_dir_all(&workdir).unwrap();
+
+        let config = RepositoryConfig::new(
+            url,
+            true,
+            None,
+            TlsConfig::default(),
+            CredentialsConfig::default(),
+            workdir.clone(),
+            None,
+        );
+        let repository = RepositoryFactory::with_config(config).create().unwrap();
+        // `HEAD` points to a branch without commit
+        assert!(repository.empty);
+        assert!(repository.get_branches().is_empty());
+
+        let repository = repository.extract_log().unwrap();
+        assert!(repository.branch_data.is_empty());
+        assert!(repository.tags.is_empty());
+        // The clone is removed all the same
+        assert_eq!(fs::read_dir(&workdir).unwrap().count(), 0);
+
+        remove_dir_all(&path).unwrap();
+    }
+}
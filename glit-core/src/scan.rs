@@ -0,0 +1,220 @@
+use crossbeam_channel::bounded;
+use futures_util::Stream;
+use rayon::ThreadPoolBuilder;
+use reqwest::Url;
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread,
+    time::Instant,
+};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tracing::{error, info};
+
+use crate::{
+    checkpoint::Checkpoint,
+    config::{CloneFilter, ConcurrencyConfig, CredentialsConfig, RepositoryConfig, TlsConfig},
+    error::{Error, Failure},
+    repo::{init_libgit2, Repository, RepositoryFactory},
+    repo_name,
+    types::{RepoName, Stage},
+};
+
+/// A repository of a scan once walked, or the failure met on it. A repository
+/// whose clone could not be deleted comes once as a cleanup failure, then once
+/// walked.
+pub type ScanItem = (RepoName, Result<Repository, Failure>);
+
+/// Callback handed each item of a scan as soon as it is walked.
+pub type Inspect<'a> = dyn FnMut(&ScanItem) + Send + 'a;
+
+/// Repositories of an org or user scan, yielded as soon as they are walked.
+///
+/// The scan runs in the background. Dropping the stream stops it: clones not
+/// started yet are skipped, the ones in progress are deleted without a walk.
+pub struct ScanStream {
+    rx: Receiver<ScanItem>,
+}
+
+impl Stream for ScanStream {
+    type Item = ScanItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Settings of a scan, taken from the org or the user.
+pub(crate) struct Scan {
+    pub repositories: Vec<Url>,
+    pub all_branches: bool,
+    pub filter: Option<CloneFilter>,
+    pub tls: TlsConfig,
+    pub credentials: CredentialsConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub workdir: PathBuf,
+    pub cache: Option<PathBuf>,
+    pub checkpoint: Option<Arc<Checkpoint>>,
+}
+
+impl Scan {
+    /// Run the scan on its own threads, the clones and walks being blocking.
+    pub fn spawn(self) -> ScanStream {
+        let (tx, rx) = mpsc::channel(self.concurrency.max_parallel_walks.max(1));
+        thread::spawn(move || self.run(tx));
+
+        ScanStream { rx }
+    }
+
+    fn run(self, items: Sender<ScanItem>) {
+        let start_a = Instant::now();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        // The consumer is gone once a send fails, nothing is left to scan for
+        let send = |item: ScanItem| {
+            if items.blocking_send(item).is_err() && !stopped.swap(true, Ordering::Relaxed) {
+                info!("Scan stopped by the consumer");
+            }
+        };
+
+        // Repositories walked by an interrupted run are taken from its state file
+        let mut repositories = Vec::new();
+        for url in self.repositories {
+            match self.checkpoint.as_ref().and_then(|c| c.get(&url)) {
+                Some(repo) => send((repo_name(&url), Ok(repo.clone()))),
+                None => repositories.push(url),
+            }
+        }
+        let repo_count = repositories.len();
+        let concurrency = self.concurrency;
+
+        // libgit2 options are process-wide, they are set before the pools use libgit2
+        if let Err(e) = init_libgit2(&self.tls) {
+            error!("libgit2 setup failed : {}", e);
+            for url in repositories {
+                send((repo_name(&url), Err(Failure::new(Stage::Clone, &e))));
+            }
+            return;
+        }
+
+        // A panic must unwind, an aborted process leaves its clones on disk
+        let clone_pool = ThreadPoolBuilder::new()
+            .num_threads(concurrency.max_parallel_clones.max(1))
+            .panic_handler(|_| error!("A clone panicked"))
+            .build()
+            .expect("Failed to build the clone thread pool");
+        let walk_pool = ThreadPoolBuilder::new()
+            .num_threads(concurrency.max_parallel_walks.max(1))
+            .build()
+            .expect("Failed to build the revwalk thread pool");
+
+        // A finished clone waits for a walker, so clones do not pile up on disk
+        let (tx, rx) = bounded(concurrency.max_parallel_walks.max(1));
+
+        for clonable_url in repositories {
+            let tx = tx.clone();
+            let stopped = stopped.clone();
+            let (tls, credentials) = (self.tls.clone(), self.credentials.clone());
+            let (workdir, cache) = (self.workdir.clone(), self.cache.clone());
+            let (all_branches, filter) = (self.all_branches, self.filter);
+
+            clone_pool.spawn(move || {
+                let name = repo_name(&clonable_url);
+                let repo = if stopped.load(Ordering::Relaxed) {
+                    None
+                } else {
+                    let repo_config = RepositoryConfig::new(
+                        clonable_url.clone(),
+                        all_branches,
+                        filter,
+                        tls,
+                        credentials,
+                        workdir,
+                        cache,
+                    );
+                    Some(RepositoryFactory::with_config(repo_config).create())
+                };
+                let _ = tx.send((name, clonable_url, repo));
+            });
+        }
+        drop(tx);
+
+        let atomic_count = AtomicUsize::new(0);
+
+        walk_pool.scope(|scope| {
+            for _ in 0..repo_count {
+                let rx = rx.clone();
+                let checkpoint = self.checkpoint.as_deref();
+                let (send, atomic_count, stopped) = (&send, &atomic_count, &stopped);
+
+                scope.spawn(move |_| {
+                    let Ok((name, url, repo)) = rx.recv() else {
+                        return;
+                    };
+                    drop(rx);
+
+                    // Once the scan is stopped, dropping the repository deletes its clone
+                    let Some(repo) = repo.filter(|_| !stopped.load(Ordering::Relaxed)) else {
+                        return;
+                    };
+                    walk(name, &url, repo, checkpoint, send);
+
+                    atomic_count.fetch_add(1, Ordering::Relaxed);
+                    info!(
+                        "Repository handled : {}/{}",
+                        atomic_count.load(Ordering::Relaxed),
+                        repo_count
+                    );
+                })
+            }
+        });
+        drop(walk_pool);
+
+        info!(
+            "Fetching and Cloning handled in {:?} for {}",
+            start_a.elapsed(),
+            repo_count
+        );
+    }
+}
+
+/// Walk and delete a clone. When both steps fail, the walk failure is the one reported.
+fn walk(
+    name: RepoName,
+    url: &Url,
+    repo: Result<Repository, Error>,
+    checkpoint: Option<&Checkpoint>,
+    send: &dyn Fn(ScanItem),
+) {
+    let report = |name: RepoName, stage: Stage, e: Error| {
+        error!("[{}] {} failed : {}", name, stage, e);
+        send((name, Err(Failure::new(stage, &e))));
+    };
+
+    let mut repo = match repo {
+        Ok(repo) => repo,
+        Err(e) => return report(name, Stage::Clone, e),
+    };
+
+    let walk = repo.walk();
+    let cleanup = repo.cleanup();
+    match (walk, cleanup) {
+        (Err(e), _) => report(name, Stage::Revwalk, e),
+        (Ok(_), cleanup) => {
+            if let Err(e) = cleanup {
+                report(name.clone(), Stage::Cleanup, e);
+            }
+            if let Some(checkpoint) = checkpoint {
+                if let Err(e) = checkpoint.record(url, &repo) {
+                    error!("[{}] Checkpoint failed : {}", name, e);
+                }
+            }
+            send((name, Ok(repo)));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Branch(pub String);
@@ -6,32 +7,110 @@ pub struct Branch(pub String);
 #[derive(Debug, Clone, PartialEq, Eq, Ord, Hash, PartialOrd, Serialize, Deserialize)]
 pub struct AuthorName(pub String);
 
-impl ToString for AuthorName {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for AuthorName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RepoName(pub String);
-impl ToString for RepoName {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for RepoName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct BranchName(pub String);
-impl ToString for BranchName {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for BranchName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TagName(pub String);
+impl fmt::Display for TagName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Mail(pub String);
-impl ToString for Mail {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for Mail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Part a git identity played in a commit, either through its signatures or
+/// through a trailer of the commit message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Author,
+    Committer,
+    CoAuthoredBy,
+    SignedOffBy,
+    ReviewedBy,
+    AckedBy,
+    TestedBy,
+    ReportedBy,
+}
+
+impl Role {
+    /// Match a trailer key (`Signed-off-by`, `co-authored-by`, ...) to its role.
+    pub fn from_trailer(key: &str) -> Option<Self> {
+        let role = match key.trim().to_ascii_lowercase().as_str() {
+            "co-authored-by" => Role::CoAuthoredBy,
+            "signed-off-by" => Role::SignedOffBy,
+            "reviewed-by" => Role::ReviewedBy,
+            "acked-by" => Role::AckedBy,
+            "tested-by" => Role::TestedBy,
+            "reported-by" => Role::ReportedBy,
+            _ => return None,
+        };
+
+        Some(role)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            Role::Author => "author",
+            Role::Committer => "committer",
+            Role::CoAuthoredBy => "co-authored-by",
+            Role::SignedOffBy => "signed-off-by",
+            Role::ReviewedBy => "reviewed-by",
+            Role::AckedBy => "acked-by",
+            Role::TestedBy => "tested-by",
+            Role::ReportedBy => "reported-by",
+        };
+        f.write_str(role)
+    }
+}
+
+/// Step of a scan at which a repository failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    Listing,
+    Clone,
+    Revwalk,
+    Cleanup,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            Stage::Listing => "listing",
+            Stage::Clone => "clone",
+            Stage::Revwalk => "revwalk",
+            Stage::Cleanup => "cleanup",
+        };
+        f.write_str(stage)
     }
 }
@@ -0,0 +1,61 @@
+use glit_core::forge::{bitbucket::Bitbucket, Forge, Owner};
+use reqwest::Url;
+
+mod common;
+
+use common::{client, mock_server, urls};
+
+fn repository(name: &str, fork: bool) -> String {
+    let parent = if fork {
+        r#","parent":{"full_name":"upstream/repo"}"#
+    } else {
+        ""
+    };
+
+    format!(
+        r#"{{"full_name":"acme/{0}","links":{{"clone":[{{"name":"https","href":"https://jdoe@bitbucket.org/acme/{0}.git"}},{{"name":"ssh","href":"git@bitbucket.org:acme/{0}.git"}}]}}{1}}}"#,
+        name, parent
+    )
+}
+
+#[tokio::test]
+async fn workspace_repositories_follow_next_field() {
+    let (base, received) = mock_server(|path, base| match path {
+        "/repositories/acme?pagelen=100" => (
+            200,
+            vec![],
+            format!(
+                r#"{{"values":[{},{}],"next":"{}repositories/acme?pagelen=100&page=2"}}"#,
+                repository("widget", false),
+                repository("upstream", true),
+                base
+            ),
+        ),
+        "/repositories/acme?pagelen=100&page=2" => (
+            200,
+            vec![],
+            format!(r#"{{"values":[{}]}}"#, repository("gadget", false)),
+        ),
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let bitbucket = Bitbucket::new(base, Some("jdoe:app-password".to_string()));
+    let repositories = bitbucket
+        .repositories(&client(), &Owner::Org("acme".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![
+            Url::parse("https://bitbucket.org/acme/widget.git").unwrap(),
+            Url::parse("https://bitbucket.org/acme/gadget.git").unwrap(),
+        ]
+    );
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|request| request.header("authorization") == Some("Basic amRvZTphcHAtcGFzc3dvcmQ=")));
+}
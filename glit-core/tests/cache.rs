@@ -0,0 +1,95 @@
+mod common;
+
+use common::{commit, repository, TempDir};
+use glit_core::{
+    cache::Cache,
+    config::{CredentialsConfig, RepositoryConfig, TlsConfig},
+    repo::{Repository, RepositoryFactory},
+};
+use reqwest::Url;
+use std::{fs, path::Path, time::Duration};
+
+fn scan(url: &Url, root: &Path) -> Repository {
+    let config = RepositoryConfig::new(
+        url.clone(),
+        false,
+        None,
+        TlsConfig::default(),
+        CredentialsConfig::default(),
+        root.join("workdir"),
+        Some(root.join("cache")),
+    );
+
+    RepositoryFactory::with_config(config)
+        .create()
+        .unwrap()
+        .extract_log()
+        .unwrap()
+}
+
+/// Number of commits of each walked mail.
+fn commit_count(repository: &Repository) -> usize {
+    repository
+        .branch_data
+        .values()
+        .flat_map(|committers| committers.committers.values())
+        .flat_map(|committer| committer.mails.values())
+        .map(|entry| entry.commits.len())
+        .sum()
+}
+
+#[test]
+fn second_scan_fetches_into_the_mirror() {
+    let dir = TempDir::new("cache");
+    let root = dir.path();
+    let url = repository(&root.join("acme/widget"), "alice");
+
+    let cache = Cache::new(root.join("cache"));
+    let mirror = cache.mirror_path(&url);
+    assert!(mirror.starts_with(root.join("cache/local")));
+    assert!(mirror.ends_with("acme/widget.git"));
+
+    assert_eq!(commit_count(&scan(&url, root)), 1);
+    assert!(mirror.join("HEAD").is_file());
+
+    // A new clone would not hold the marker
+    fs::write(mirror.join("marker"), "").unwrap();
+    commit(
+        &git2::Repository::open(root.join("acme/widget")).unwrap(),
+        "bob",
+    );
+
+    assert_eq!(commit_count(&scan(&url, root)), 2);
+    assert!(mirror.join("marker").is_file());
+
+    let mirrors = cache.list().unwrap();
+    assert_eq!(mirrors.len(), 1);
+    assert_eq!(mirrors[0].path, mirror);
+}
+
+#[test]
+fn prune_and_clear_only_delete_mirrors() {
+    let dir = TempDir::new("cache-prune");
+    let root = dir.path();
+    let url = repository(&root.join("acme/widget"), "alice");
+    scan(&url, root);
+
+    let cache = Cache::new(root.join("cache"));
+    let mirror = cache.mirror_path(&url);
+    fs::write(root.join("cache/notes.txt"), "").unwrap();
+
+    // Fetched just now
+    assert!(cache.prune(Duration::from_secs(3600)).unwrap().is_empty());
+    assert!(mirror.is_dir());
+
+    // Last fetched at the epoch
+    fs::write(mirror.join("glit-last-fetch"), "0").unwrap();
+    let pruned = cache.prune(Duration::from_secs(3600)).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert!(!mirror.exists());
+
+    scan(&url, root);
+    assert_eq!(cache.clear().unwrap().len(), 1);
+    assert!(!mirror.exists());
+    assert!(root.join("cache/notes.txt").is_file());
+}
@@ -0,0 +1,75 @@
+use glit_core::{
+    config::RepositoryConfig,
+    repo::{remove_live_clones, CloneGuard, RepositoryFactory},
+};
+use std::{
+    fs,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+mod common;
+
+use common::{repository, TempDir};
+
+/// `remove_live_clones` deletes the clones of every test, they run one at a time.
+fn serial() -> MutexGuard<'static, ()> {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    SERIAL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[test]
+fn dropped_guard_deletes_its_clone() {
+    let _serial = serial();
+    let dir = TempDir::new("guard-drop");
+    let clone = dir.join("clone");
+    fs::create_dir_all(clone.join("objects")).unwrap();
+
+    let guard = CloneGuard::new(clone.clone());
+    assert!(clone.is_dir());
+    drop(guard);
+    assert!(!clone.exists());
+
+    // A kept clone is no longer tracked
+    fs::create_dir_all(&clone).unwrap();
+    CloneGuard::new(clone.clone()).keep();
+    remove_live_clones();
+    assert!(clone.is_dir());
+}
+
+#[test]
+fn live_clones_are_deleted_on_interrupt() {
+    let _serial = serial();
+    let dir = TempDir::new("guard-live");
+    let clones = ["a", "b"].map(|name| dir.join(name));
+    let guards = clones.clone().map(|clone| {
+        fs::create_dir_all(&clone).unwrap();
+        CloneGuard::new(clone)
+    });
+
+    remove_live_clones();
+    for clone in &clones {
+        assert!(!clone.exists(), "{:?} not deleted", clone);
+    }
+
+    // Guards dropped afterwards find nothing to delete
+    drop(guards);
+}
+
+#[test]
+fn local_repositories_are_never_deleted() {
+    let _serial = serial();
+    let dir = TempDir::new("guard-local");
+    let path = dir.join("widget");
+    repository(&path, "alice");
+
+    let repo = RepositoryFactory::with_config(RepositoryConfig::local(path.clone()))
+        .create()
+        .unwrap();
+    remove_live_clones();
+    assert!(path.join(".git").is_dir());
+
+    let repo = repo.extract_log().unwrap();
+    assert_eq!(repo.branch_data.len(), 1);
+    drop(repo);
+    assert!(path.join(".git").is_dir());
+}
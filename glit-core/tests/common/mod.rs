@@ -0,0 +1,166 @@
+#![allow(dead_code)]
+
+use glit_core::{config::HttpConfig, forge::RemoteRepository, http::HttpClient};
+use reqwest::{Client, Url};
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Status, extra headers and body of a mocked response.
+pub type Response = (u16, Vec<String>, String);
+
+/// Request received by the mock server.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Every request received, in order.
+pub type Received = Arc<Mutex<Vec<Request>>>;
+
+/// Minimal HTTP server, `respond` gets the requested path and the server base url.
+pub fn mock_server<F>(respond: F) -> (Url, Received)
+where
+    F: Fn(&str, &Url) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let received: Received = Arc::new(Mutex::new(Vec::new()));
+
+    let server_base = base.clone();
+    let log = received.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+
+            let path = request_line.split(' ').nth(1).unwrap().to_string();
+            log.lock().unwrap().push(Request {
+                path: path.clone(),
+                headers,
+            });
+
+            let (status, headers, body) = respond(&path, &server_base);
+            let mut response = format!(
+                "HTTP/1.1 {} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                status,
+                body.len()
+            );
+            for header in headers {
+                response.push_str(&format!("{}\r\n", header));
+            }
+            response.push_str("\r\n");
+            response.push_str(&body);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (base, received)
+}
+
+/// Clone urls of a listing, in order.
+pub fn urls(repositories: &[RemoteRepository]) -> Vec<Url> {
+    repositories
+        .iter()
+        .map(|repository| repository.url.clone())
+        .collect()
+}
+
+/// Client retrying without the production delays.
+pub fn client() -> HttpClient {
+    let config = HttpConfig {
+        max_retries: 2,
+        base_delay: Duration::from_millis(10),
+        ..HttpConfig::default()
+    };
+
+    HttpClient::with_config(Client::new(), config)
+}
+
+/// Folder of a test in the system temporary folder, deleted when dropped so that
+/// a failed assertion does not leave it behind.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("glit-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Add a commit of `author` on top of `HEAD`, the first one of an empty repository.
+pub fn commit(repo: &git2::Repository, author: &str) -> git2::Oid {
+    let signature = git2::Signature::now(author, &format!("{}@example.com", author)).unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        author,
+        &tree,
+        parent.iter().collect::<Vec<_>>().as_slice(),
+    )
+    .unwrap()
+}
+
+/// Repository at `path` with a single commit of `author`, as a url.
+pub fn repository(path: &Path, author: &str) -> Url {
+    let repo = git2::Repository::init(path).unwrap();
+    commit(&repo, author);
+
+    Url::from_directory_path(path).unwrap()
+}
@@ -0,0 +1,55 @@
+use glit_core::forge::{gitea::Gitea, Forge, Owner};
+use reqwest::Url;
+
+mod common;
+
+use common::{client, mock_server, urls};
+
+fn repository(name: &str, fork: bool) -> String {
+    format!(
+        r#"{{"full_name":"forgejo/{0}","clone_url":"https://codeberg.org/forgejo/{0}.git","fork":{1}}}"#,
+        name, fork
+    )
+}
+
+#[tokio::test]
+async fn org_repositories_follow_link_pagination() {
+    let (base, received) = mock_server(|path, base| match path {
+        "/api/v1/orgs/forgejo/repos?limit=50" => (
+            200,
+            vec![format!(
+                r#"Link: <{}api/v1/orgs/forgejo/repos?limit=50&page=2>; rel="next""#,
+                base
+            )],
+            format!(
+                "[{},{}]",
+                repository("forgejo", false),
+                repository("mirror", true)
+            ),
+        ),
+        "/api/v1/orgs/forgejo/repos?limit=50&page=2" => {
+            (200, vec![], format!("[{}]", repository("runner", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let gitea = Gitea::for_instance(&base, Some("secret".to_string())).unwrap();
+    let repositories = gitea
+        .repositories(&client(), &Owner::Org("forgejo".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![
+            Url::parse("https://codeberg.org/forgejo/forgejo.git").unwrap(),
+            Url::parse("https://codeberg.org/forgejo/runner.git").unwrap(),
+        ]
+    );
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|request| request.header("authorization") == Some("token secret")));
+}
@@ -0,0 +1,237 @@
+use glit_core::{
+    forge::{github::Github, Forge, ForgeKind, Owner, Visibility},
+    Error,
+};
+use reqwest::Url;
+
+mod common;
+
+use common::{client, mock_server, urls};
+
+fn repository(name: &str, fork: bool) -> String {
+    format!(
+        r#"{{"name":"{0}","clone_url":"https://github.com/acme/{0}.git","fork":{1}}}"#,
+        name, fork
+    )
+}
+
+#[tokio::test]
+async fn org_repositories_follow_link_pagination() {
+    let (base, received) = mock_server(|path, base| match path {
+        "/orgs/acme/repos?type=sources&per_page=100" => (
+            200,
+            vec![format!(
+                r#"Link: <{0}orgs/acme/repos?type=sources&per_page=100&page=2>; rel="next", <{0}orgs/acme/repos?type=sources&per_page=100&page=2>; rel="last""#,
+                base
+            )],
+            format!(
+                "[{},{}]",
+                repository("widget", false),
+                repository("spoon", true)
+            ),
+        ),
+        "/orgs/acme/repos?type=sources&per_page=100&page=2" => {
+            (200, vec![], format!("[{}]", repository("gadget", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let github = Github::new(base, Some("secret".to_string()));
+    let repositories = github
+        .repositories(&client(), &Owner::Org("acme".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![
+            Url::parse("https://github.com/acme/widget.git").unwrap(),
+            Url::parse("https://github.com/acme/gadget.git").unwrap(),
+        ]
+    );
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|request| request.header("authorization") == Some("Bearer secret")));
+}
+
+#[tokio::test]
+async fn user_repositories_without_token() {
+    let (base, received) = mock_server(|path, _| match path {
+        "/users/jdoe/repos?type=owner&per_page=100" => {
+            (200, vec![], format!("[{}]", repository("dotfiles", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let github = Github::new(base, None);
+    let repositories = github
+        .repositories(&client(), &Owner::User("jdoe".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![Url::parse("https://github.com/acme/dotfiles.git").unwrap()]
+    );
+    assert_eq!(received.lock().unwrap()[0].header("authorization"), None);
+}
+
+#[tokio::test]
+async fn org_repositories_keep_their_visibility() {
+    let (base, _) = mock_server(|path, _| {
+        match path {
+        "/orgs/acme/repos?type=sources&per_page=100" => (
+            200,
+            vec![],
+            r#"[
+                {"name":"site","clone_url":"https://github.com/acme/site.git","fork":false,"private":false,"visibility":"public"},
+                {"name":"infra","clone_url":"https://github.com/acme/infra.git","fork":false,"private":true,"visibility":"private"},
+                {"name":"tools","clone_url":"https://github.com/acme/tools.git","fork":false,"private":true,"visibility":"internal"},
+                {"name":"legacy","clone_url":"https://github.com/acme/legacy.git","fork":false,"private":true}
+            ]"#
+            .to_string(),
+        ),
+        _ => (404, vec![], "{}".to_string()),
+    }
+    });
+
+    let github = Github::new(base, Some("secret".to_string()));
+    let repositories = github
+        .repositories(&client(), &Owner::Org("acme".to_string()))
+        .await
+        .unwrap();
+
+    let visibilities = repositories
+        .iter()
+        .map(|repository| repository.visibility)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        visibilities,
+        vec![
+            Visibility::Public,
+            Visibility::Private,
+            Visibility::Internal,
+            Visibility::Private
+        ]
+    );
+}
+
+#[tokio::test]
+async fn own_user_repositories_include_private_ones() {
+    let (base, received) = mock_server(|path, _| {
+        match path {
+        "/user" => (200, vec![], r#"{"login":"JDoe"}"#.to_string()),
+        "/user/repos?affiliation=owner&per_page=100" => (
+            200,
+            vec![],
+            r#"[{"name":"notes","clone_url":"https://github.com/jdoe/notes.git","fork":false,"private":true}]"#
+                .to_string(),
+        ),
+        _ => (404, vec![], "{}".to_string()),
+    }
+    });
+
+    let github = Github::new(base, Some("secret".to_string()));
+    let repositories = github
+        .repositories(&client(), &Owner::User("jdoe".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![Url::parse("https://github.com/jdoe/notes.git").unwrap()]
+    );
+    assert_eq!(repositories[0].visibility, Visibility::Private);
+    assert_eq!(received.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn api_error_is_reported() {
+    let (base, _) = mock_server(|_, _| {
+        (
+            403,
+            vec![],
+            r#"{"message":"API rate limit exceeded"}"#.to_string(),
+        )
+    });
+
+    let github = Github::new(base, None);
+    let repositories = github
+        .repositories(&client(), &Owner::Org("acme".to_string()))
+        .await;
+
+    assert!(matches!(repositories, Err(Error::Network(_))));
+}
+
+#[tokio::test]
+async fn exhausted_rate_limit_is_reported() {
+    let (base, _) = mock_server(|_, _| {
+        (
+            403,
+            vec!["X-RateLimit-Remaining: 0".to_string()],
+            r#"{"message":"API rate limit exceeded"}"#.to_string(),
+        )
+    });
+
+    let github = Github::new(base, None);
+    let repositories = github
+        .repositories(&client(), &Owner::Org("acme".to_string()))
+        .await;
+
+    assert!(matches!(repositories, Err(Error::RateLimited(_))));
+}
+
+#[tokio::test]
+async fn enterprise_server_api_under_api_v3() {
+    let (base, received) = mock_server(|path, _| match path {
+        "/api/v3/orgs/security/repos?type=sources&per_page=100" => {
+            (200, vec![], format!("[{}]", repository("audit", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    // Any host other than github.com is an Enterprise Server
+    let org_url = base.join("security/").unwrap();
+    let github = Github::for_instance(&org_url, Some("secret".to_string())).unwrap();
+    let repositories = github
+        .repositories(&client(), &Owner::Org("security".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![Url::parse("https://github.com/acme/audit.git").unwrap()]
+    );
+    assert_eq!(
+        received.lock().unwrap()[0].header("authorization"),
+        Some("Bearer secret")
+    );
+}
+
+#[tokio::test]
+async fn api_url_without_trailing_slash_keeps_its_path() {
+    let (base, _) = mock_server(|path, _| match path {
+        "/api/v3/orgs/security/repos?type=sources&per_page=100" => {
+            (200, vec![], format!("[{}]", repository("audit", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let org_url = base.join("security/").unwrap();
+    let api_url = base.join("api/v3").unwrap();
+    let github = ForgeKind::Github
+        .forge(&org_url, None, Some(api_url))
+        .unwrap();
+    let repositories = github
+        .repositories(&client(), &Owner::Org("security".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![Url::parse("https://github.com/acme/audit.git").unwrap()]
+    );
+}
@@ -0,0 +1,124 @@
+use glit_core::forge::{gitlab::Gitlab, Forge, Owner, Visibility};
+use reqwest::Url;
+
+mod common;
+
+use common::{client, mock_server, urls};
+
+fn project(path: &str, fork: bool) -> String {
+    let forked_from = if fork {
+        r#","forked_from_project":{"id":1}"#
+    } else {
+        ""
+    };
+
+    format!(
+        r#"{{"path_with_namespace":"{0}","http_url_to_repo":"https://gitlab.com/{0}.git"{1}}}"#,
+        path, forked_from
+    )
+}
+
+#[tokio::test]
+async fn group_projects_include_subgroups() {
+    let (base, received) = mock_server(|path, base| match path {
+        "/api/v4/groups/acme%2Fplatform/projects?include_subgroups=true&per_page=100" => (
+            200,
+            vec![format!(
+                r#"Link: <{}api/v4/groups/acme%2Fplatform/projects?include_subgroups=true&per_page=100&page=2>; rel="next""#,
+                base
+            )],
+            format!(
+                "[{},{}]",
+                project("acme/platform/api", false),
+                project("acme/platform/upstream", true)
+            ),
+        ),
+        "/api/v4/groups/acme%2Fplatform/projects?include_subgroups=true&per_page=100&page=2" => (
+            200,
+            vec![],
+            format!("[{}]", project("acme/platform/infra/deploy", false)),
+        ),
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let gitlab = Gitlab::for_instance(&base, Some("secret".to_string())).unwrap();
+    let repositories = gitlab
+        .repositories(&client(), &Owner::Org("acme/platform".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![
+            Url::parse("https://gitlab.com/acme/platform/api.git").unwrap(),
+            Url::parse("https://gitlab.com/acme/platform/infra/deploy.git").unwrap(),
+        ]
+    );
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|request| request.header("private-token") == Some("secret")));
+}
+
+#[tokio::test]
+async fn user_projects() {
+    let (base, _) = mock_server(|path, _| match path {
+        "/api/v4/users/jdoe/projects?per_page=100" => {
+            (200, vec![], format!("[{}]", project("jdoe/notes", false)))
+        }
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let gitlab = Gitlab::for_instance(&base, None).unwrap();
+    let repositories = gitlab
+        .repositories(&client(), &Owner::User("jdoe".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        urls(&repositories),
+        vec![Url::parse("https://gitlab.com/jdoe/notes.git").unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn projects_without_visibility_are_unknown() {
+    let (base, _) = mock_server(|path, _| {
+        match path {
+        "/api/v4/users/jdoe/projects?per_page=100" => (
+            200,
+            vec![],
+            r#"[
+                {"path_with_namespace":"jdoe/site","http_url_to_repo":"https://gitlab.com/jdoe/site.git","visibility":"public"},
+                {"path_with_namespace":"jdoe/infra","http_url_to_repo":"https://gitlab.com/jdoe/infra.git","visibility":"private"},
+                {"path_with_namespace":"jdoe/notes","http_url_to_repo":"https://gitlab.com/jdoe/notes.git"},
+                {"path_with_namespace":"jdoe/draft","http_url_to_repo":"https://gitlab.com/jdoe/draft.git","visibility":"secret"}
+            ]"#
+            .to_string(),
+        ),
+        _ => (404, vec![], "{}".to_string()),
+    }
+    });
+
+    let gitlab = Gitlab::for_instance(&base, None).unwrap();
+    let repositories = gitlab
+        .repositories(&client(), &Owner::User("jdoe".to_string()))
+        .await
+        .unwrap();
+
+    let visibilities = repositories
+        .iter()
+        .map(|repository| repository.visibility)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        visibilities,
+        vec![
+            Visibility::Public,
+            Visibility::Private,
+            Visibility::Unknown,
+            Visibility::Unknown
+        ]
+    );
+}
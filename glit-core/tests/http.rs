@@ -0,0 +1,55 @@
+use glit_core::Error;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+mod common;
+
+use common::{client, mock_server};
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let calls = AtomicUsize::new(0);
+    let (base, received) = mock_server(move |_, _| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 | 1 => (503, vec![], "{}".to_string()),
+        _ => (200, vec![], "[]".to_string()),
+    });
+
+    let client = client();
+    let resp = client.send(client.get(base)).await.unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(received.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn secondary_rate_limit_waits_for_retry_after() {
+    let calls = AtomicUsize::new(0);
+    let (base, _) = mock_server(move |_, _| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 => (
+            403,
+            vec!["Retry-After: 1".to_string()],
+            r#"{"message":"You have exceeded a secondary rate limit"}"#.to_string(),
+        ),
+        _ => (200, vec![], "[]".to_string()),
+    });
+
+    let client = client();
+    let start = Instant::now();
+    let resp = client.send(client.get(base)).await.unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn rate_limit_is_reported_once_retries_are_spent() {
+    let (base, received) = mock_server(|_, _| (429, vec![], "{}".to_string()));
+
+    let client = client();
+    let resp = client.send(client.get(base)).await;
+
+    assert!(matches!(resp, Err(Error::RateLimited(_))));
+    assert_eq!(received.lock().unwrap().len(), 3);
+}
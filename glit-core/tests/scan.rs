@@ -0,0 +1,221 @@
+use futures_util::StreamExt;
+use glit_core::{
+    checkpoint::Checkpoint,
+    config::{CheckpointConfig, ConcurrencyConfig, CredentialsConfig, OrgConfig, TlsConfig},
+    forge::ForgeKind,
+    org::{Org, OrgFactory},
+    types::Stage,
+    ExtractLog,
+};
+use reqwest::Url;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+mod common;
+
+use common::{client, mock_server, repository, TempDir};
+
+fn org_config(url: Url, workdir: PathBuf) -> OrgConfig {
+    OrgConfig {
+        url,
+        all_branches: false,
+        filter: None,
+        credentials: CredentialsConfig::default(),
+        forge: Some(ForgeKind::Gitea),
+        api_url: None,
+        visibility: None,
+        concurrency: ConcurrencyConfig::default(),
+        tls: TlsConfig::default(),
+        workdir,
+        cache: None,
+        checkpoint: None,
+    }
+}
+
+/// Org listing `names` from a mock Gitea, `missing` not being on disk.
+async fn org(root: &Path, names: &[&str], concurrency: ConcurrencyConfig) -> Org {
+    let urls = names
+        .iter()
+        .map(|name| match *name {
+            "missing" => Url::from_directory_path(root.join(name)).unwrap(),
+            _ => repository(&root.join(name), "alice"),
+        })
+        .collect::<Vec<_>>();
+
+    listed_org(root, &urls, concurrency, None).await
+}
+
+/// Org listing the repositories at `urls` from a mock Gitea.
+async fn listed_org(
+    root: &Path,
+    urls: &[Url],
+    concurrency: ConcurrencyConfig,
+    checkpoint: Option<CheckpointConfig>,
+) -> Org {
+    let repositories = urls
+        .iter()
+        .map(|url| {
+            format!(
+                r#"{{"full_name":"acme/{}","clone_url":"{}","fork":false,"private":false}}"#,
+                url.path_segments()
+                    .unwrap()
+                    .rfind(|s| !s.is_empty())
+                    .unwrap(),
+                url
+            )
+        })
+        .collect::<Vec<_>>();
+    let listing = format!("[{}]", repositories.join(","));
+
+    let (base, _) = mock_server(move |path, _| match path {
+        "/api/v1/orgs/acme/repos?limit=50" => (200, vec![], listing.clone()),
+        _ => (404, vec![], "{}".to_string()),
+    });
+
+    let workdir = root.join("workdir");
+    fs::create_dir_all(&workdir).unwrap();
+    let config = OrgConfig {
+        concurrency,
+        checkpoint,
+        ..org_config(base.join("acme/").unwrap(), workdir)
+    };
+
+    OrgFactory::with_config(config)
+        .unwrap()
+        .build_with_client(&client())
+        .await
+}
+
+#[tokio::test]
+async fn repositories_are_streamed_as_they_are_walked() {
+    let dir = TempDir::new("scan");
+    let root = dir.path();
+    let org = org(root, &["widget", "missing"], ConcurrencyConfig::default()).await;
+
+    let mut items = org.log_stream().collect::<Vec<_>>().await;
+    items.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+    assert_eq!(items.len(), 2);
+    let (name, failure) = &items[0];
+    assert!(name.0.ends_with("/missing"));
+    assert_eq!(failure.as_ref().unwrap_err().stage, Stage::Clone);
+    let (name, repo) = &items[1];
+    assert!(name.0.ends_with("/widget"));
+    assert_eq!(repo.as_ref().unwrap().branch_data.len(), 1);
+
+    // Walked clones are deleted
+    assert_eq!(fs::read_dir(root.join("workdir")).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn dropped_stream_stops_the_scan() {
+    let dir = TempDir::new("scan-stop");
+    let root = dir.path();
+    let names = ["a", "b", "c", "d", "e", "f"];
+    let concurrency = ConcurrencyConfig {
+        max_parallel_clones: 1,
+        max_parallel_walks: 1,
+    };
+    let org = org(root, &names, concurrency).await;
+
+    let mut stream = org.log_stream();
+    let (_, first) = stream.next().await.unwrap();
+    assert!(first.is_ok());
+    drop(stream);
+
+    // Clones in progress are deleted once the scan sees the stream is gone
+    let workdir = root.join("workdir");
+    for _ in 0..50 {
+        if fs::read_dir(&workdir).unwrap().count() == 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(fs::read_dir(&workdir).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn extract_log_with_inspects_every_repository() {
+    let dir = TempDir::new("scan-inspect");
+    let root = dir.path();
+    let org = org(root, &["widget", "missing"], ConcurrencyConfig::default()).await;
+
+    let mut inspected = Vec::new();
+    let org = org
+        .extract_log_with(&client(), &mut |(name, _)| inspected.push(name.0.clone()))
+        .await;
+    inspected.sort();
+
+    assert_eq!(inspected.len(), 2);
+    assert!(inspected[0].ends_with("/missing"));
+    assert!(inspected[1].ends_with("/widget"));
+    assert_eq!(org.repositories_data.len(), 1);
+    assert_eq!(org.failures.len(), 1);
+}
+
+#[tokio::test]
+async fn same_named_repositories_are_kept_apart() {
+    let dir = TempDir::new("scan-names");
+    let root = dir.path();
+    let org = org(root, &["a/api", "b/api"], ConcurrencyConfig::default()).await;
+
+    let org = org.extract_log(&client()).await;
+
+    let mut names = org
+        .repositories_data
+        .iter()
+        .map(|repo| repo.key().0.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names.len(), 2);
+    assert!(names[0].ends_with("/a/api"));
+    assert!(names[1].ends_with("/b/api"));
+}
+
+#[tokio::test]
+async fn resumed_scan_skips_the_checkpointed_repositories() {
+    let dir = TempDir::new("scan-resume");
+    let root = dir.path();
+    let state = root.join("scan.state");
+    let widget = repository(&root.join("widget"), "alice");
+    let gadget = repository(&root.join("gadget"), "bob");
+    let missing = Url::from_directory_path(root.join("missing")).unwrap();
+
+    let checkpoint = |resume| {
+        Some(CheckpointConfig {
+            path: state.clone(),
+            resume,
+        })
+    };
+    let urls = [widget.clone(), gadget.clone(), missing.clone()];
+    let org = listed_org(root, &urls, ConcurrencyConfig::default(), checkpoint(false)).await;
+    let org = org.extract_log(&client()).await;
+    assert_eq!(org.repositories_data.len(), 2);
+
+    // Failed repositories are not recorded
+    assert_eq!(fs::read_to_string(&state).unwrap().lines().count(), 2);
+
+    // Killed while writing a line
+    let mut content = fs::read_to_string(&state).unwrap();
+    content.push_str(r#"{"url":"file:///cut"#);
+    fs::write(&state, content).unwrap();
+
+    // Checkpointed repositories are not cloned again
+    fs::remove_dir_all(root.join("widget")).unwrap();
+    let late = repository(&root.join("late"), "carol");
+    let urls = [widget.clone(), gadget.clone(), late.clone()];
+    let org = listed_org(root, &urls, ConcurrencyConfig::default(), checkpoint(true)).await;
+    let org = org.extract_log(&client()).await;
+    assert_eq!(org.repositories_data.len(), 3);
+    assert!(org.failures.is_empty());
+
+    let resumed = Checkpoint::resume(&state).unwrap();
+    for url in [&widget, &gadget, &late] {
+        assert!(resumed.get(url).is_some(), "{} not recorded", url);
+    }
+    assert!(resumed.get(&missing).is_none());
+}